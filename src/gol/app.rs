@@ -2,50 +2,129 @@
 
 use ggez::conf;
 use ggez::event::{self, EventHandler};
-use ggez::graphics::{self, DrawMode, DrawParam, Mesh};
+use ggez::graphics::{self, DrawMode, DrawParam, Mesh, Rect};
 use ggez::input::{
     self,
     keyboard::{KeyCode, KeyMods},
     mouse::MouseButton,
 };
+use ggez::timer;
 use ggez::{Context, ContextBuilder, GameResult};
 
-use super::gui::{render_ui, ImGuiWrapper, UiState};
-use super::logic::GameState;
-use super::renderer::{ImageRenderer, Renderer};
+use super::gui::{render_ui, ImGuiWrapper, UiState, MAX_GRID_SIDE};
+use super::logic::{
+    GameState, GameStats, TrackedObject, Workspace, ANALYSIS_BUDGET, MAX_SPEED, MIN_SPEED,
+    REPLAY_BUDGET,
+};
+use super::renderer::{Camera, ImageRenderer, Recording, Renderer};
+
+/// Zoom factor for each mouse wheel step
+const ZOOM_STEP: f32 = 1.25;
 
 pub struct App {
     game_state: GameState,
     ui_state: UiState,
     image: ImageRenderer,
     imgui_wrapper: ImGuiWrapper,
-    scale: f32,
+    workspace: Workspace,
+    /// Animation being recorded
+    recording: Option<Recording>,
+    drawing: bool,
+    /// Set while the mouse button used to pick an object is held
+    picking: bool,
+    /// Set while the middle mouse button drags the view
+    panning: bool,
+    /// Edit counter of the state after the last resize following the window
+    resize_edits: Option<usize>,
 }
 
 impl App {
     pub fn new(ctx: &mut Context, scale: f32, hidpi_factor: f32) -> Self {
         let win_size = graphics::size(ctx);
-        let game_size = ((win_size.0 / scale) as usize, (win_size.1 / scale) as usize);
-        let mut game_state = GameState::new(game_size);
+        let mut game_state =
+            GameState::new(((win_size.0 / scale) as usize, (win_size.1 / scale) as usize));
+        game_state.fit_size_to_rule();
+        let game_size = game_state.grid_size();
         let mut image = ImageRenderer::new();
         image.set_size((win_size.0 as usize, win_size.1 as usize));
+        image.camera = Some(Camera::fit(game_size, image.size));
         game_state.randomize();
+        let mut ui_state = UiState::new();
+        ui_state.cell_scale = scale;
 
         Self {
             game_state,
-            ui_state: UiState::new(),
+            ui_state,
             image,
             imgui_wrapper: ImGuiWrapper::new(ctx, hidpi_factor),
-            scale,
+            workspace: Workspace::new(),
+            recording: None,
+            drawing: false,
+            picking: false,
+            panning: false,
+            resize_edits: None,
         }
     }
 
-    pub fn screen_pos_to_game(&self, mouse_position: (f32, f32)) -> (usize, usize) {
-        let scale = self.image.scale(&self.game_state);
-        (
-            (mouse_position.0 / scale.0 as f32) as usize,
-            (mouse_position.1 / scale.1 as f32) as usize,
-        )
+    pub fn step(&mut self, count: usize) {
+        self.game_state.running = false;
+        self.game_state.scheduler.step(count);
+    }
+
+    pub fn change_speed(&mut self, factor: f32) {
+        let speed = self.game_state.scheduler.speed * factor;
+        self.game_state.scheduler.speed = speed.clamp(MIN_SPEED, MAX_SPEED);
+    }
+
+    pub fn undo(&mut self) {
+        self.game_state.running = false;
+        self.workspace.history.undo(&mut self.game_state);
+    }
+
+    pub fn redo(&mut self) {
+        self.game_state.running = false;
+        self.workspace.history.redo(&mut self.game_state);
+    }
+
+    /// Show the whole grid
+    pub fn zoom_to_fit(&mut self) {
+        self.image.camera = Some(Camera::fit(self.game_state.grid_size(), self.image.size));
+    }
+
+    /// Show the grid at the cell scale, centered
+    pub fn apply_cell_scale(&mut self) {
+        self.image.camera = Some(Camera::centered(
+            self.ui_state.cell_scale,
+            self.game_state.grid_size(),
+            self.image.size,
+        ));
+    }
+
+    /// Zoom by a factor around a screen position
+    pub fn zoom(&mut self, factor: f32, anchor: (f32, f32)) {
+        let grid_size = self.game_state.grid_size();
+        let screen_size = self.image.size;
+        if let Some(camera) = &mut self.image.camera {
+            camera.zoom_at(factor, anchor);
+            camera.clamp(grid_size, screen_size);
+        }
+    }
+
+    /// Move the view by a distance in pixels
+    pub fn pan(&mut self, delta: (f32, f32)) {
+        let grid_size = self.game_state.grid_size();
+        let screen_size = self.image.size;
+        if let Some(camera) = &mut self.image.camera {
+            camera.pan(delta);
+            camera.clamp(grid_size, screen_size);
+        }
+    }
+
+    /// Get the cell under a screen position, through the camera, or `None`
+    /// outside of the grid
+    pub fn screen_pos_to_game(&self, mouse_position: (f32, f32)) -> Option<(usize, usize)> {
+        self.image
+            .screen_pos_to_cell(&self.game_state, mouse_position)
     }
 }
 
@@ -57,19 +136,76 @@ impl EventHandler for App {
             let mouse_right_pressed =
                 input::mouse::button_pressed(ctx, input::mouse::MouseButton::Right);
 
-            if mouse_left_pressed || mouse_right_pressed {
+            if mouse_left_pressed && self.ui_state.picking_object {
+                // Select an object instead of drawing
                 let mouse_position = input::mouse::position(ctx);
-                let game_pos = self.screen_pos_to_game((mouse_position.x, mouse_position.y));
-                self.game_state.set_value_at_pos_with_radius(
-                    game_pos,
-                    self.ui_state.cursor_size.max(1),
-                    mouse_left_pressed,
-                );
+                if let Some(game_pos) =
+                    self.screen_pos_to_game((mouse_position.x, mouse_position.y))
+                {
+                    self.workspace.tracked = TrackedObject::pick(&self.game_state, game_pos);
+                    self.ui_state.picking_object = false;
+                }
+                self.picking = true;
+            } else if self.picking {
+                self.picking = mouse_left_pressed;
+            } else if mouse_left_pressed || mouse_right_pressed {
+                let mouse_position = input::mouse::position(ctx);
+                // Clicks outside of the grid do not draw
+                if let Some(game_pos) =
+                    self.screen_pos_to_game((mouse_position.x, mouse_position.y))
+                {
+                    // Group the whole brush stroke in one history entry
+                    if !self.drawing {
+                        self.workspace.history.record("Brush", &self.game_state);
+                        self.drawing = true;
+                    }
+
+                    self.game_state.set_value_at_pos_with_radius(
+                        game_pos,
+                        self.ui_state.cursor_size.max(1),
+                        mouse_left_pressed,
+                        self.ui_state.brush_species,
+                    );
+                    self.game_state.stats = GameStats::from_state(&self.game_state);
+                }
+            } else {
+                self.drawing = false;
             }
         }
 
+        // Spread the selected object analysis over frames
+        if let Some(tracked) = &mut self.workspace.tracked {
+            tracked.analyze(ANALYSIS_BUDGET);
+        }
+        // Also spread the timeline replay
+        self.workspace
+            .timeline
+            .replay(&mut self.game_state, REPLAY_BUDGET);
+
+        // Watch for periodicity only when a window shows it
+        self.game_state.detect_cycles = self.ui_state.show_motion || self.ui_state.show_census;
+
         // Cycle
-        self.game_state.cycle();
+        let ticks = self
+            .game_state
+            .scheduled_ticks(timer::delta(ctx).as_secs_f32());
+        if ticks > 0 {
+            self.workspace.history.record_run(&self.game_state);
+        }
+        // Sample every generation, not only the last one of the frame
+        let workspace = &mut self.workspace;
+        let recording = &mut self.recording;
+        self.game_state.cycle_with(ticks, |state| {
+            workspace.record(state);
+            if let Some(recording) = recording {
+                recording.record(state);
+            }
+        });
+        // Also track edits made while paused, and the first recorded frame
+        self.workspace.record(&self.game_state);
+        if let Some(recording) = &mut self.recording {
+            recording.record(&self.game_state);
+        }
 
         Ok(())
     }
@@ -82,9 +218,37 @@ impl EventHandler for App {
         _repeat: bool,
     ) {
         if !self.imgui_wrapper.keyboard_captured {
+            let ctrl = keymods.contains(KeyMods::CTRL);
+            let shift = keymods.contains(KeyMods::SHIFT);
+
             match keycode {
-                KeyCode::Space => self.game_state.running = !self.game_state.running,
-                KeyCode::Return => self.game_state.randomize(),
+                KeyCode::Space => {
+                    self.game_state.running = !self.game_state.running;
+                    self.game_state.scheduler.cancel();
+                }
+                KeyCode::S | KeyCode::Right => self.step(1),
+                KeyCode::N => self.step(self.ui_state.step_count.max(1) as usize),
+                KeyCode::G => {
+                    self.game_state.running = false;
+                    self.game_state
+                        .scheduler
+                        .run_until(self.ui_state.target_generation.max(0) as usize);
+                }
+                KeyCode::Add | KeyCode::Equals => self.change_speed(2.0),
+                KeyCode::Subtract | KeyCode::Minus => self.change_speed(0.5),
+                KeyCode::Return => {
+                    self.workspace.history.record("Randomize", &self.game_state);
+                    self.game_state.randomize();
+                }
+                KeyCode::Back if self.game_state.can_tick_back() => {
+                    self.workspace.history.record("Step back", &self.game_state);
+                    self.game_state.running = false;
+                    self.game_state.tick_back();
+                }
+                KeyCode::F => self.zoom_to_fit(),
+                KeyCode::Z if ctrl && shift => self.redo(),
+                KeyCode::Z if ctrl => self.undo(),
+                KeyCode::Y if ctrl => self.redo(),
                 _ => {}
             }
         }
@@ -100,8 +264,12 @@ impl EventHandler for App {
         self.imgui_wrapper.update_text(val);
     }
 
-    fn mouse_motion_event(&mut self, _ctx: &mut Context, x: f32, y: f32, _dx: f32, _dy: f32) {
+    fn mouse_motion_event(&mut self, _ctx: &mut Context, x: f32, y: f32, dx: f32, dy: f32) {
         self.imgui_wrapper.update_mouse_pos(x, y);
+
+        if self.panning {
+            self.pan((dx, dy));
+        }
     }
 
     fn mouse_button_down_event(
@@ -111,6 +279,10 @@ impl EventHandler for App {
         _x: f32,
         _y: f32,
     ) {
+        if button == MouseButton::Middle && !self.imgui_wrapper.mouse_captured {
+            self.panning = true;
+        }
+
         self.imgui_wrapper.update_mouse_down((
             button == MouseButton::Left,
             button == MouseButton::Right,
@@ -118,26 +290,61 @@ impl EventHandler for App {
         ));
     }
 
-    fn mouse_button_up_event(
-        &mut self,
-        _ctx: &mut Context,
-        _button: MouseButton,
-        _x: f32,
-        _y: f32,
-    ) {
+    fn mouse_button_up_event(&mut self, _ctx: &mut Context, button: MouseButton, _x: f32, _y: f32) {
+        if button == MouseButton::Middle {
+            self.panning = false;
+        }
+
         self.imgui_wrapper.update_mouse_down((false, false, false));
     }
 
-    fn mouse_wheel_event(&mut self, _ctx: &mut Context, x: f32, y: f32) {
+    fn mouse_wheel_event(&mut self, ctx: &mut Context, x: f32, y: f32) {
         self.imgui_wrapper.update_mouse_wheel(x, y);
 
         if !self.imgui_wrapper.mouse_captured {
-            if self.ui_state.cursor_size as f32 + y < 1.0 {
-                self.ui_state.cursor_size = 1
-            } else if self.ui_state.cursor_size as f32 + y > 100.0 {
-                self.ui_state.cursor_size = 100;
+            if input::keyboard::active_mods(ctx).contains(KeyMods::CTRL) {
+                let mouse_position = input::mouse::position(ctx);
+                self.zoom(ZOOM_STEP.powf(y), (mouse_position.x, mouse_position.y));
             } else {
-                self.ui_state.cursor_size += y as usize;
+                // The wheel alone changes the brush size
+                if self.ui_state.cursor_size as f32 + y < 1.0 {
+                    self.ui_state.cursor_size = 1
+                } else if self.ui_state.cursor_size as f32 + y > 100.0 {
+                    self.ui_state.cursor_size = 100;
+                } else {
+                    self.ui_state.cursor_size += y as usize;
+                }
+            }
+        }
+    }
+
+    fn resize_event(&mut self, ctx: &mut Context, width: f32, height: f32) {
+        // Keep one screen unit per pixel, instead of stretching
+        if let Err(e) = graphics::set_screen_coordinates(ctx, Rect::new(0.0, 0.0, width, height)) {
+            eprintln!("Could not resize the screen: {}", e);
+        }
+        self.image.set_size((width as usize, height as usize));
+
+        if self.ui_state.grid_follows_window {
+            let scale = self.ui_state.cell_scale;
+            let side = |pixels: f32| ((pixels / scale) as usize).clamp(1, MAX_GRID_SIDE as usize);
+            let size = self.game_state.valid_size((side(width), side(height)));
+            if size != (self.game_state.width, self.game_state.height) {
+                // Undo a whole window drag at once
+                let history = &mut self.workspace.history;
+                let dragging = self.resize_edits == Some(self.game_state.edits)
+                    && history.undo_label() == Some("Resize grid");
+                if !dragging {
+                    history.record("Resize grid", &self.game_state);
+                }
+                self.game_state.resize(size, self.ui_state.grid_anchor);
+                self.resize_edits = Some(self.game_state.edits);
+            }
+            self.apply_cell_scale();
+        } else {
+            let grid_size = self.game_state.grid_size();
+            if let Some(camera) = &mut self.image.camera {
+                camera.clamp(grid_size, self.image.size);
             }
         }
     }
@@ -158,12 +365,13 @@ impl EventHandler for App {
         graphics::draw(ctx, &image, DrawParam::default())?;
 
         // Cursor target
+        let zoom = self.image.camera.map_or(1.0, |camera| camera.zoom);
         if !self.imgui_wrapper.mouse_captured {
             let circle = Mesh::new_circle(
                 ctx,
                 DrawMode::Fill(Default::default()),
                 input::mouse::position(ctx),
-                (self.ui_state.cursor_size as f32 / 2.0 * self.scale).max(1.0),
+                (self.ui_state.cursor_size as f32 / 2.0 * zoom).max(1.0),
                 0.5,
                 graphics::Color::from_rgba(0, 255, 0, 64),
             )?;
@@ -171,14 +379,32 @@ impl EventHandler for App {
         }
 
         let mut game_state = self.game_state.clone();
+        self.ui_state.zoom = zoom;
         let mut ui_state = self.ui_state.clone();
+        let workspace = &mut self.workspace;
+        let recording = &mut self.recording;
 
         self.imgui_wrapper.render(ctx, |ui, nctx| {
-            render_ui(ui, nctx, &mut game_state, &mut ui_state);
+            render_ui(
+                ui,
+                nctx,
+                &mut game_state,
+                &mut ui_state,
+                workspace,
+                recording,
+            );
         });
 
         self.game_state = game_state;
         self.ui_state = ui_state;
+        if self.ui_state.zoom_to_fit {
+            self.ui_state.zoom_to_fit = false;
+            self.zoom_to_fit();
+        }
+        if self.ui_state.apply_cell_scale {
+            self.ui_state.apply_cell_scale = false;
+            self.apply_cell_scale();
+        }
 
         graphics::present(ctx)
     }
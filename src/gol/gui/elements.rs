@@ -1,3 +1,8 @@
+use std::borrow::Cow;
+use std::env;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
 use ggez::event;
 use ggez::graphics;
 use ggez::timer;
@@ -6,9 +11,60 @@ use ggez::Context;
 use imgui::*;
 use imgui_gfx_renderer::*;
 
-use super::super::logic::GameState;
+use super::super::logic::{
+    common_name, Anchor, BitmapImport, BitmapScale, Census, GameState, GameStats, History, Motion,
+    PopulationHistory, Rule, Symmetry, Timeline, TrackedObject, VolumeRule, VolumeView, Workspace,
+    MAX_SPEED, MIN_SPEED,
+};
+use super::super::renderer::{
+    ImageRenderer, PngOptions, Recording, RecordingOptions, MAX_ZOOM, MIN_ZOOM,
+    RECORDING_MEMORY_BUDGET,
+};
 use super::state::UiState;
 
+/// Largest grid width or height, in cells
+pub const MAX_GRID_SIDE: i32 = 4096;
+// Export files are written in the working directory, with the export time
+// added to their name
+
+/// Census export file
+pub const CENSUS_FILE: &str = "census.csv";
+/// Population history CSV export file
+pub const POPULATION_CSV_FILE: &str = "population.csv";
+/// Population history JSON export file
+pub const POPULATION_JSON_FILE: &str = "population.json";
+/// PNG export file
+pub const PNG_FILE: &str = "snapshot.png";
+/// GIF recording file
+pub const RECORDING_GIF_FILE: &str = "recording.gif";
+/// APNG recording file
+pub const RECORDING_APNG_FILE: &str = "recording.png";
+
+/// Get a new path for an export file in the working directory, so earlier
+/// exports are kept
+fn export_path(file: &str) -> PathBuf {
+    let timestamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |time| time.as_secs());
+    let dir = env::current_dir().unwrap_or_default();
+    let file = Path::new(file);
+    let stem = file
+        .file_stem()
+        .and_then(|s| s.to_str())
+        .unwrap_or("export");
+    let extension = file.extension().and_then(|e| e.to_str()).unwrap_or("");
+
+    // Several exports in the same second get a counter
+    (1..)
+        .map(|n| match n {
+            1 => format!("{}-{}.{}", stem, timestamp, extension),
+            n => format!("{}-{}-{}.{}", stem, timestamp, n, extension),
+        })
+        .map(|name| dir.join(name))
+        .find(|path| !path.exists())
+        .unwrap()
+}
+
 fn show_help_marker(ui: &Ui, desc: &str) {
     ui.text_disabled(im_str!("(?)"));
     if ui.is_item_hovered() {
@@ -18,29 +74,95 @@ fn show_help_marker(ui: &Ui, desc: &str) {
     }
 }
 
-fn draw_main_menu(ui: &Ui, ctx: &mut Context, _game_state: &mut GameState, ui_state: &mut UiState) {
+fn draw_main_menu(
+    ui: &Ui,
+    ctx: &mut Context,
+    game_state: &mut GameState,
+    ui_state: &mut UiState,
+    history: &mut History,
+) {
     ui.main_menu_bar(|| {
         ui.menu(im_str!("File"), true, || {
+            if MenuItem::new(im_str!("Import image")).build(ui) {
+                ui_state.show_import_image = true;
+            }
+            if MenuItem::new(im_str!("Export PNG")).build(ui) {
+                ui_state.show_export_png = true;
+            }
+            if MenuItem::new(im_str!("Record animation")).build(ui) {
+                ui_state.show_recording = true;
+            }
             if MenuItem::new(im_str!("Quit"))
                 .shortcut(im_str!("ALT+F4"))
-                .build(&ui)
+                .build(ui)
             {
                 event::quit(ctx);
             }
         });
+        ui.menu(im_str!("Edit"), true, || {
+            let undo_label = history.undo_label();
+            if MenuItem::new(&im_str!("Undo {}", undo_label.unwrap_or("")))
+                .shortcut(im_str!("CTRL+Z"))
+                .enabled(undo_label.is_some())
+                .build(ui)
+            {
+                game_state.running = false;
+                history.undo(game_state);
+            }
+
+            let redo_label = history.redo_label();
+            if MenuItem::new(&im_str!("Redo {}", redo_label.unwrap_or("")))
+                .shortcut(im_str!("CTRL+Y"))
+                .enabled(redo_label.is_some())
+                .build(ui)
+            {
+                game_state.running = false;
+                history.redo(game_state);
+            }
+        });
+        ui.menu(im_str!("View"), true, || {
+            if MenuItem::new(im_str!("Zoom to fit"))
+                .shortcut(im_str!("F"))
+                .build(ui)
+            {
+                ui_state.zoom_to_fit = true;
+            }
+            if MenuItem::new(im_str!("Grid size")).build(ui) {
+                let (width, height) = (game_state.width as i32, game_state.height as i32);
+                ui_state.grid_size = [width, height];
+                ui_state.show_grid_size = true;
+            }
+            ui.separator();
+            ui.text_disabled(&im_str!("Zoom: {:.0}%", ui_state.zoom * 100.0));
+        });
         ui.menu(im_str!("Tools"), true, || {
-            if MenuItem::new(im_str!("Settings")).build(&ui) {
+            if MenuItem::new(im_str!("Settings")).build(ui) {
                 ui_state.show_window = true;
             }
+            if MenuItem::new(im_str!("Timeline")).build(ui) {
+                ui_state.show_timeline = true;
+            }
+            if MenuItem::new(im_str!("Census")).build(ui) {
+                ui_state.show_census = true;
+            }
+            if MenuItem::new(im_str!("Population")).build(ui) {
+                ui_state.show_population = true;
+            }
+            if MenuItem::new(im_str!("Statistics")).build(ui) {
+                ui_state.show_stats = true;
+            }
+            if MenuItem::new(im_str!("Spaceship detector")).build(ui) {
+                ui_state.show_motion = true;
+            }
         });
         ui.menu(im_str!("Help"), true, || {
-            if MenuItem::new(im_str!("Show help")).build(&ui) {
+            if MenuItem::new(im_str!("Show help")).build(ui) {
                 ui_state.show_help = true;
             }
 
             ui.separator();
 
-            if MenuItem::new(im_str!("About")).build(&ui) {
+            if MenuItem::new(im_str!("About")).build(ui) {
                 ui_state.show_about = true;
             }
         });
@@ -52,9 +174,16 @@ fn draw_settings_window(
     ctx: &mut Context,
     game_state: &mut GameState,
     ui_state: &mut UiState,
+    history: &mut History,
 ) {
     // Configuration window
     if ui_state.show_window {
+        let brush_species = &mut ui_state.brush_species;
+        let volume_rule = &mut ui_state.volume_rule;
+        let step_count = &mut ui_state.step_count;
+        let target_generation = &mut ui_state.target_generation;
+        let soup_seed = &mut ui_state.soup_seed;
+        let soup_region = &mut ui_state.soup_region;
         Window::new(im_str!("caw settings"))
             .position([50.0, 50.0], Condition::Always)
             .position_pivot([0.0, 0.0])
@@ -62,7 +191,7 @@ fn draw_settings_window(
             .resizable(false)
             .opened(&mut ui_state.show_window)
             .collapsible(true)
-            .build(&ui, || {
+            .build(ui, || {
                 ui.text(im_str!("Rendering"));
                 ui.separator();
                 ui.text(im_str!(" FPS: {:2.0}", timer::fps(ctx)));
@@ -72,23 +201,983 @@ fn draw_settings_window(
                 ui.text(im_str!(" Current ticks: {}", game_state.current_tick));
                 ui.text(im_str!(" Moving cells: {}", game_state.stats.moving));
                 ui.text(im_str!(" Stopped cells: {}", game_state.stats.stopped));
+                match game_state.stats.periodicity {
+                    Some(p) if p.is_still() => {
+                        ui.text(im_str!(" Stable since generation {}", p.start))
+                    }
+                    Some(p) if p.is_moving() => ui.text(im_str!(
+                        " Period: {} since generation {}, displacement ({}, {})",
+                        p.period,
+                        p.start,
+                        p.displacement.0,
+                        p.displacement.1
+                    )),
+                    Some(p) => ui.text(im_str!(
+                        " Period: {} since generation {}",
+                        p.period,
+                        p.start
+                    )),
+                    None if game_state.watches_cycles() => {
+                        ui.text(im_str!(" Period: not detected"))
+                    }
+                    None => ui.text(im_str!(" Period: not watched")),
+                }
+                ui.checkbox(im_str!("Pause when periodic"), &mut game_state.auto_pause);
+                ui.same_line(0.0);
+                show_help_marker(
+                    ui,
+                    "Pause the simulation when the grid becomes periodic. The period is also \
+                     watched while the spaceship detector or census is open",
+                );
+                if game_state.rule.species_count() > 1 {
+                    for (species, count) in game_state.stats.species.iter().enumerate() {
+                        ui.text(im_str!(" Species {} cells: {}", species + 1, count));
+                    }
+                }
+                ui.separator();
+                ui.text(im_str!("Rule"));
+                ui.separator();
+                draw_rule_selector(ui, game_state, history);
+                draw_species_selector(ui, game_state, brush_species);
+                draw_volume_settings(ui, game_state, history, volume_rule);
                 ui.separator();
                 ui.text(im_str!("Actions"));
                 ui.separator();
                 // Is running
-                ui.checkbox(im_str!("Running"), &mut game_state.running);
+                if ui.checkbox(im_str!("Running"), &mut game_state.running) {
+                    game_state.scheduler.cancel();
+                }
                 ui.same_line(0.0);
-                show_help_marker(&ui, "Pause or resume simulation state");
+                show_help_marker(ui, "Pause or resume simulation state");
+
+                draw_step_controls(ui, game_state, step_count, target_generation);
 
                 // Randomize state
                 if ui.button(im_str!("Randomize"), [100.0, 20.0]) {
+                    history.record("Randomize", game_state);
                     game_state.randomize();
                 }
                 ui.same_line(0.0);
                 // Clear state
                 if ui.button(im_str!("Clear"), [100.0, 20.0]) {
+                    history.record("Clear", game_state);
                     game_state.clear();
                 }
+                draw_soup_settings(ui, game_state, history, soup_seed, soup_region);
+
+                // Step back
+                if game_state.rule.is_reversible() {
+                    if ui.button(im_str!("Step back"), [100.0, 20.0]) && game_state.can_tick_back()
+                    {
+                        history.record("Step back", game_state);
+                        game_state.running = false;
+                        game_state.tick_back();
+                    }
+                    ui.same_line(0.0);
+                    show_help_marker(ui, "Run the reversible rule one generation backwards");
+                }
+            });
+    }
+}
+
+fn draw_step_controls(
+    ui: &Ui,
+    game_state: &mut GameState,
+    step_count: &mut i32,
+    target_generation: &mut i32,
+) {
+    // Speed
+    ui.set_next_item_width(200.0);
+    Slider::new(im_str!("Speed"), MIN_SPEED..=MAX_SPEED)
+        .display_format(im_str!("%.1f gen/s"))
+        .power(4.0)
+        .build(ui, &mut game_state.scheduler.speed);
+
+    // Step
+    if ui.button(im_str!("Step"), [100.0, 20.0]) {
+        game_state.running = false;
+        game_state.scheduler.step(1);
+    }
+    ui.same_line(0.0);
+    if ui.button(im_str!("Step N"), [100.0, 20.0]) {
+        game_state.running = false;
+        game_state.scheduler.step((*step_count).max(1) as usize);
+    }
+    ui.same_line(0.0);
+    ui.set_next_item_width(100.0);
+    ui.input_int(im_str!("##step_count"), step_count).build();
+
+    // Run until
+    if ui.button(im_str!("Run until"), [100.0, 20.0]) {
+        game_state.running = false;
+        game_state
+            .scheduler
+            .run_until((*target_generation).max(0) as usize);
+    }
+    ui.same_line(0.0);
+    ui.set_next_item_width(100.0);
+    ui.input_int(im_str!("##target_generation"), target_generation)
+        .build();
+    if let Some(target) = game_state.scheduler.target_tick {
+        ui.same_line(0.0);
+        ui.text(im_str!("running to {}", target));
+    }
+}
+
+fn draw_soup_settings(
+    ui: &Ui,
+    game_state: &mut GameState,
+    history: &mut History,
+    soup_seed: &mut ImString,
+    soup_region: &mut [i32; 4],
+) {
+    ui.text(im_str!(" Soup seed: {}", game_state.soup.seed));
+
+    ui.set_next_item_width(200.0);
+    ui.input_text(im_str!("##soup_seed"), soup_seed).build();
+    ui.same_line(0.0);
+    if ui.button(im_str!("Replay seed"), [0.0, 0.0]) {
+        if let Ok(seed) = soup_seed.to_str().trim().parse::<u64>() {
+            history.record("Randomize", game_state);
+            game_state.randomize_with_seed(seed);
+        }
+    }
+    ui.same_line(0.0);
+    show_help_marker(ui, "Fill the grid again with the soup of a given seed");
+
+    ui.set_next_item_width(200.0);
+    Slider::new(im_str!("Density"), 0.0..=1.0)
+        .display_format(im_str!("%.2f"))
+        .build(ui, &mut game_state.soup.density);
+
+    let symmetries = Symmetry::all();
+    let mut current = symmetries
+        .iter()
+        .position(|symmetry| *symmetry == game_state.soup.symmetry)
+        .unwrap_or(0);
+    ui.set_next_item_width(200.0);
+    if ComboBox::new(im_str!("Symmetry")).build_simple(
+        ui,
+        &mut current,
+        &symmetries,
+        &|symmetry: &Symmetry| Cow::Owned(ImString::new(symmetry.to_string())),
+    ) {
+        game_state.soup.symmetry = symmetries[current];
+    }
+
+    let mut restricted = game_state.soup.region.is_some();
+    ui.checkbox(im_str!("Restrict to region"), &mut restricted);
+    if restricted {
+        ui.set_next_item_width(200.0);
+        ui.input_int4(im_str!("x, y, w, h"), soup_region).build();
+        for value in soup_region.iter_mut() {
+            *value = (*value).max(0);
+        }
+        let [x, y, width, height] = *soup_region;
+        game_state.soup.region = Some((x as usize, y as usize, width as usize, height as usize));
+    } else {
+        game_state.soup.region = None;
+    }
+}
+
+fn draw_rule_selector(ui: &Ui, game_state: &mut GameState, history: &mut History) {
+    let rules = Rule::presets();
+    let mut current = rules
+        .iter()
+        .position(|rule| *rule == game_state.rule)
+        .unwrap_or(rules.len());
+    let preview = ImString::new(game_state.rule.name());
+
+    if ComboBox::new(im_str!("##rule"))
+        .preview_value(&preview)
+        .build_simple(ui, &mut current, &rules, &|rule: &Rule| {
+            Cow::Owned(ImString::new(rule.name()))
+        })
+    {
+        history.record("Change rule", game_state);
+        game_state.set_rule(rules[current]);
+    }
+}
+
+fn draw_volume_settings(
+    ui: &Ui,
+    game_state: &mut GameState,
+    history: &mut History,
+    volume_rule: &mut ImString,
+) {
+    let rule = match game_state.rule {
+        Rule::Volume(rule) => rule,
+        _ => return,
+    };
+
+    ui.text(im_str!("3D rule: {}", rule));
+    ui.input_text(im_str!("##volume_rule"), volume_rule).build();
+    ui.same_line(0.0);
+    if ui.button(im_str!("Apply"), [60.0, 20.0]) {
+        if let Some(rule) = VolumeRule::parse(volume_rule.to_str()) {
+            history.record("Change rule", game_state);
+            game_state.set_rule(Rule::Volume(rule));
+        }
+    }
+    ui.same_line(0.0);
+    show_help_marker(
+        ui,
+        "Survival/Birth/States/Neighborhood (M or N), like 4/4/5/M",
+    );
+
+    if let Some(volume) = game_state.volume.as_mut() {
+        let views = [
+            (VolumeView::Slice, im_str!("Z slice")),
+            (VolumeView::MaxProjection, im_str!("Max projection")),
+            (VolumeView::DensityProjection, im_str!("Density projection")),
+        ];
+        for (idx, (view, label)) in views.iter().enumerate() {
+            if idx > 0 {
+                ui.same_line(0.0);
+            }
+            ui.radio_button(label, &mut volume.view, *view);
+        }
+
+        let mut slice = volume.slice as i32;
+        if Slider::new(im_str!("Slice"), 0..=volume.depth as i32 - 1).build(ui, &mut slice) {
+            volume.slice = slice as usize;
+        }
+        ui.text(im_str!(" 3D cells: {}", volume.population()));
+    }
+}
+
+fn draw_species_selector(ui: &Ui, game_state: &mut GameState, brush_species: &mut u8) {
+    let species_count = game_state.rule.species_count();
+    if species_count < 2 {
+        return;
+    }
+
+    *brush_species = (*brush_species).min(species_count - 1);
+    ui.text(im_str!("Brush species"));
+    for species in 0..species_count {
+        if species > 0 {
+            ui.same_line(0.0);
+        }
+        ui.radio_button(&im_str!("{}##species", species + 1), brush_species, species);
+    }
+}
+
+fn draw_timeline_window(
+    ui: &Ui,
+    ctx: &mut Context,
+    game_state: &mut GameState,
+    ui_state: &mut UiState,
+    history: &mut History,
+    timeline: &mut Timeline,
+) {
+    let (_win_w, win_h) = graphics::size(ctx);
+
+    if ui_state.show_timeline {
+        let seeking = &mut ui_state.seeking;
+        Window::new(im_str!("Timeline"))
+            .position([50.0, win_h - 50.0], Condition::Always)
+            .position_pivot([0.0, 1.0])
+            .opened(&mut ui_state.show_timeline)
+            .movable(false)
+            .resizable(false)
+            .collapsible(true)
+            .build(ui, || {
+                let first_tick = timeline.first_tick().unwrap_or(0) as i32;
+                let latest_tick = timeline.latest_tick() as i32;
+                let mut tick = timeline.target().unwrap_or(game_state.current_tick) as i32;
+
+                ui.set_next_item_width(400.0);
+                if Slider::new(
+                    im_str!("Generation"),
+                    first_tick..=latest_tick.max(first_tick),
+                )
+                .build(ui, &mut tick)
+                {
+                    // Group the whole drag in one history entry
+                    if !*seeking {
+                        history.record("Seek", game_state);
+                        *seeking = true;
+                    }
+                    timeline.seek(game_state, tick as usize);
+                }
+                if !ui.is_item_active() {
+                    *seeking = false;
+                }
+                ui.same_line(0.0);
+                show_help_marker(ui, "Drag to go back to any generation since the last edit");
+                if let Some(target) = timeline.target() {
+                    ui.text(im_str!(
+                        " Replaying to generation {} (at {})",
+                        target,
+                        game_state.current_tick
+                    ));
+                }
+
+                ui.text(im_str!(
+                    " Keyframes: {} ({:.1} MB)",
+                    timeline.keyframes_count(),
+                    timeline.memory_used() as f32 / 1024.0 / 1024.0
+                ));
+
+                let mut interval = timeline.interval() as i32;
+                ui.set_next_item_width(100.0);
+                if ui
+                    .input_int(im_str!("Keyframe interval"), &mut interval)
+                    .build()
+                {
+                    timeline.set_interval(interval.max(1) as usize);
+                }
+
+                let mut budget = (timeline.memory_budget() / 1024 / 1024) as i32;
+                ui.set_next_item_width(100.0);
+                if ui
+                    .input_int(im_str!("Memory budget (MB)"), &mut budget)
+                    .build()
+                {
+                    timeline.set_memory_budget(budget.max(1) as usize * 1024 * 1024);
+                }
+            });
+    }
+}
+
+fn draw_census_window(
+    ui: &Ui,
+    ctx: &mut Context,
+    game_state: &mut GameState,
+    ui_state: &mut UiState,
+    history: &mut History,
+    census: &mut Option<Census>,
+) {
+    let (win_w, _win_h) = graphics::size(ctx);
+
+    if ui_state.show_census {
+        let census_status = &mut ui_state.census_status;
+        let apgcode = &mut ui_state.apgcode;
+        Window::new(im_str!("Census"))
+            .position([win_w - 50.0, 50.0], Condition::Appearing)
+            .position_pivot([1.0, 0.0])
+            .size([450.0, 400.0], Condition::Appearing)
+            .opened(&mut ui_state.show_census)
+            .collapsible(true)
+            .build(ui, || {
+                if game_state.rule != Rule::Life {
+                    ui.text_disabled(im_str!("Census is only available for the Game of Life"));
+                    return;
+                }
+
+                ui.set_next_item_width(200.0);
+                ui.input_text(im_str!("apgcode"), apgcode).build();
+                ui.same_line(0.0);
+                if ui.button(im_str!("Paste"), [0.0, 0.0]) {
+                    let (width, height) = game_state.grid_size();
+                    let mut pasted = game_state.clone();
+                    if pasted.paste_apgcode(apgcode.to_str(), (width / 2, height / 2)) {
+                        history.record("Paste", game_state);
+                        *game_state = pasted;
+                    } else {
+                        *census_status = format!("Invalid apgcode: {}", apgcode.to_str());
+                    }
+                }
+                ui.same_line(0.0);
+                if ui.button(im_str!("Identify"), [0.0, 0.0]) {
+                    match game_state.apgcode() {
+                        Some(code) => *apgcode = ImString::new(code),
+                        None => *census_status = "Grid is not a single object".to_string(),
+                    }
+                }
+                ui.same_line(0.0);
+                show_help_marker(
+                    ui,
+                    "Paste an object at the center of the grid from its apgcode, \
+                     or get the apgcode of the whole grid",
+                );
+                ui.separator();
+
+                if ui.button(im_str!("Take census"), [0.0, 0.0]) {
+                    *census = Some(Census::from_state(game_state));
+                }
+                ui.same_line(0.0);
+                show_help_marker(
+                    ui,
+                    "A census is also taken automatically when the grid stabilizes",
+                );
+
+                let census = match census {
+                    Some(census) => census,
+                    None => return,
+                };
+
+                ui.same_line(0.0);
+                if ui.button(im_str!("Export CSV"), [0.0, 0.0]) {
+                    let path = export_path(CENSUS_FILE);
+                    *census_status = match census.write_csv(&path) {
+                        Ok(()) => format!("Exported to {}", path.display()),
+                        Err(e) => format!("Export failed: {}", e),
+                    };
+                }
+                if !census_status.is_empty() {
+                    ui.text_disabled(&im_str!("{}", census_status));
+                }
+
+                ui.text(im_str!(
+                    " Generation {}: {} objects",
+                    census.generation,
+                    census.total()
+                ));
+                ui.separator();
+
+                ui.columns(3, im_str!("census"), true);
+                ui.text(im_str!("Count"));
+                ui.next_column();
+                ui.text(im_str!("Name"));
+                ui.next_column();
+                ui.text(im_str!("apgcode"));
+                ui.next_column();
+                ui.separator();
+                for (code, count) in census.sorted() {
+                    ui.text(im_str!("{}", count));
+                    ui.next_column();
+                    ui.text(common_name(code).unwrap_or("-"));
+                    ui.next_column();
+                    ui.text(code);
+                    ui.next_column();
+                }
+                ui.columns(1, im_str!("census"), false);
+            });
+    }
+}
+
+fn draw_population_window(
+    ui: &Ui,
+    ctx: &mut Context,
+    game_state: &mut GameState,
+    ui_state: &mut UiState,
+    population: &mut PopulationHistory,
+) {
+    let (win_w, win_h) = graphics::size(ctx);
+
+    if ui_state.show_population {
+        let population_status = &mut ui_state.population_status;
+        let plot_samples = &mut ui_state.plot_samples;
+        Window::new(im_str!("Population"))
+            .position([win_w - 50.0, win_h - 50.0], Condition::Appearing)
+            .position_pivot([1.0, 1.0])
+            .opened(&mut ui_state.show_population)
+            .collapsible(true)
+            .build(ui, || {
+                let count = (*plot_samples).max(2) as usize;
+                let plot = |label: &ImStr, values: Vec<f32>| {
+                    let last = values.last().copied().unwrap_or(0.0);
+                    ui.plot_lines(label, &values)
+                        .overlay_text(&im_str!("{}", last))
+                        .scale_min(0.0)
+                        .graph_size([400.0, 60.0])
+                        .build();
+                };
+
+                plot(
+                    im_str!("Population"),
+                    population.series(count, |s| s.population),
+                );
+                plot(im_str!("Births"), population.series(count, |s| s.births));
+                plot(im_str!("Deaths"), population.series(count, |s| s.deaths));
+                plot(im_str!("Moving"), population.series(count, |s| s.moving));
+                plot(im_str!("Stopped"), population.series(count, |s| s.stopped));
+
+                ui.set_next_item_width(100.0);
+                ui.input_int(im_str!("Samples shown"), plot_samples).build();
+                ui.text(im_str!(
+                    " {} samples recorded since generation {}",
+                    population.len(),
+                    population
+                        .samples()
+                        .next()
+                        .map_or(game_state.current_tick, |s| s.generation)
+                ));
+                ui.separator();
+
+                if ui.button(im_str!("Export CSV"), [0.0, 0.0]) {
+                    let path = export_path(POPULATION_CSV_FILE);
+                    *population_status = match population.write_csv(&path) {
+                        Ok(()) => format!("Exported to {}", path.display()),
+                        Err(e) => format!("Export failed: {}", e),
+                    };
+                }
+                ui.same_line(0.0);
+                if ui.button(im_str!("Export JSON"), [0.0, 0.0]) {
+                    let path = export_path(POPULATION_JSON_FILE);
+                    *population_status = match population.write_json(&path) {
+                        Ok(()) => format!("Exported to {}", path.display()),
+                        Err(e) => format!("Export failed: {}", e),
+                    };
+                }
+                ui.same_line(0.0);
+                if ui.button(im_str!("Clear"), [0.0, 0.0]) {
+                    population.clear();
+                }
+                if !population_status.is_empty() {
+                    ui.text_disabled(&im_str!("{}", population_status));
+                }
+            });
+    }
+}
+
+fn draw_stats_window(
+    ui: &Ui,
+    ctx: &mut Context,
+    game_state: &mut GameState,
+    ui_state: &mut UiState,
+) {
+    let (win_w, _win_h) = graphics::size(ctx);
+
+    if ui_state.show_stats {
+        let entropy_block_sizes = &mut ui_state.entropy_block_sizes;
+        Window::new(im_str!("Statistics"))
+            .position([win_w / 2.0, 50.0], Condition::Appearing)
+            .position_pivot([0.5, 0.0])
+            .opened(&mut ui_state.show_stats)
+            .collapsible(true)
+            .build(ui, || {
+                let stats = &game_state.stats;
+                ui.text(im_str!(" Generation: {}", game_state.current_tick));
+                ui.text(im_str!(" Population: {}", stats.population));
+                ui.text(im_str!(" Births: {}", stats.births));
+                ui.text(im_str!(" Deaths: {}", stats.deaths));
+                ui.text(im_str!(" Density: {:.2}%", stats.density * 100.0));
+                match stats.bounding_box {
+                    Some((min_x, min_y, max_x, max_y)) => ui.text(im_str!(
+                        " Bounding box: ({}, {}) to ({}, {}), {}x{}",
+                        min_x,
+                        min_y,
+                        max_x,
+                        max_y,
+                        max_x - min_x + 1,
+                        max_y - min_y + 1
+                    )),
+                    None => ui.text(im_str!(" Bounding box: empty")),
+                }
+                match stats.center_of_mass {
+                    Some((x, y)) => ui.text(im_str!(" Center of mass: ({:.1}, {:.1})", x, y)),
+                    None => ui.text(im_str!(" Center of mass: none")),
+                }
+                ui.separator();
+
+                ui.text(im_str!("Spatial entropy"));
+                ui.same_line(0.0);
+                show_help_marker(
+                    ui,
+                    "Shannon entropy of the population of grid blocks, in bits",
+                );
+                for block in &stats.entropy {
+                    ui.text(im_str!(
+                        " {0}x{0} blocks: {1:.3} bits",
+                        block.block_size,
+                        block.entropy
+                    ));
+                }
+
+                ui.set_next_item_width(100.0);
+                if ui
+                    .input_text(im_str!("Block sizes"), entropy_block_sizes)
+                    .build()
+                {
+                    game_state.entropy_block_sizes = entropy_block_sizes
+                        .to_str()
+                        .split(',')
+                        .filter_map(|size| size.trim().parse::<usize>().ok())
+                        .filter(|size| *size > 0)
+                        .collect();
+                    game_state.stats = GameStats::from_state(game_state);
+                }
+            });
+    }
+}
+
+fn draw_grid_size_window(
+    ui: &Ui,
+    ctx: &mut Context,
+    game_state: &mut GameState,
+    ui_state: &mut UiState,
+    history: &mut History,
+) {
+    let (win_w, win_h) = graphics::size(ctx);
+
+    if ui_state.show_grid_size {
+        let grid_size = &mut ui_state.grid_size;
+        let anchor = &mut ui_state.grid_anchor;
+        let cell_scale = &mut ui_state.cell_scale;
+        let follows_window = &mut ui_state.grid_follows_window;
+        let apply_cell_scale = &mut ui_state.apply_cell_scale;
+        let status = &mut ui_state.grid_size_status;
+        Window::new(im_str!("Grid size"))
+            .position([win_w / 2.0, win_h / 2.0], Condition::Appearing)
+            .position_pivot([0.5, 0.5])
+            .opened(&mut ui_state.show_grid_size)
+            .collapsible(true)
+            .build(ui, || {
+                ui.text(im_str!(
+                    " Current size: {}x{}",
+                    game_state.width,
+                    game_state.height
+                ));
+                if game_state.active_volume().is_some() {
+                    ui.text_disabled(im_str!("3D rules keep their own grid size"));
+                }
+                ui.separator();
+
+                ui.set_next_item_width(200.0);
+                ui.input_int2(im_str!("Width, height"), grid_size).build();
+                for value in grid_size.iter_mut() {
+                    *value = (*value).clamp(1, MAX_GRID_SIDE);
+                }
+
+                let anchors = Anchor::all();
+                let mut current = anchors
+                    .iter()
+                    .position(|value| value == anchor)
+                    .unwrap_or(0);
+                ui.set_next_item_width(200.0);
+                if ComboBox::new(im_str!("Anchor")).build_simple(
+                    ui,
+                    &mut current,
+                    &anchors,
+                    &|anchor: &Anchor| Cow::Owned(ImString::new(anchor.to_string())),
+                ) {
+                    *anchor = anchors[current];
+                }
+                ui.same_line(0.0);
+                show_help_marker(ui, "Part of the grid which stays in place");
+
+                ui.set_next_item_width(200.0);
+                Slider::new(im_str!("Cell scale"), MIN_ZOOM..=MAX_ZOOM)
+                    .display_format(im_str!("%.2f"))
+                    .build(ui, cell_scale);
+                ui.same_line(0.0);
+                show_help_marker(ui, "Pixels per cell");
+                if ui.button(im_str!("Fit to window"), [0.0, 0.0]) {
+                    *grid_size = [
+                        ((win_w / *cell_scale) as i32).clamp(1, MAX_GRID_SIDE),
+                        ((win_h / *cell_scale) as i32).clamp(1, MAX_GRID_SIDE),
+                    ];
+                }
+                ui.checkbox(im_str!("Follow window size"), follows_window);
+                ui.same_line(0.0);
+                show_help_marker(ui, "Resize the grid with the window, at the cell scale");
+                ui.separator();
+
+                if ui.button(im_str!("Apply"), [0.0, 0.0]) {
+                    let size =
+                        game_state.valid_size((grid_size[0] as usize, grid_size[1] as usize));
+                    if size != (game_state.width, game_state.height) {
+                        history.record("Resize grid", game_state);
+                        game_state.resize(size, *anchor);
+                    }
+                    *apply_cell_scale = true;
+                    *status = format!("Grid resized to {}x{}", size.0, size.1);
+                }
+                if !status.is_empty() {
+                    ui.text_disabled(&im_str!("{}", status));
+                }
+            });
+    }
+}
+
+fn draw_import_image_window(
+    ui: &Ui,
+    ctx: &mut Context,
+    game_state: &mut GameState,
+    ui_state: &mut UiState,
+    history: &mut History,
+) {
+    let (win_w, win_h) = graphics::size(ctx);
+
+    if ui_state.show_import_image {
+        let import_path = &mut ui_state.import_path;
+        let settings = &mut ui_state.bitmap_import;
+        let import_status = &mut ui_state.import_status;
+        Window::new(im_str!("Import image"))
+            .position([win_w / 2.0, win_h / 2.0], Condition::Appearing)
+            .position_pivot([0.5, 0.5])
+            .opened(&mut ui_state.show_import_image)
+            .collapsible(true)
+            .build(ui, || {
+                ui.set_next_item_width(300.0);
+                ui.input_text(im_str!("File"), import_path).build();
+                ui.same_line(0.0);
+                show_help_marker(ui, "PNG, JPEG, GIF or BMP image");
+
+                ui.set_next_item_width(200.0);
+                Slider::new(im_str!("Threshold"), 0.0..=1.0)
+                    .display_format(im_str!("%.2f"))
+                    .build(ui, &mut settings.threshold);
+                ui.same_line(0.0);
+                show_help_marker(ui, "Pixels darker than the threshold become live cells");
+                ui.checkbox(im_str!("Dithering"), &mut settings.dither);
+                ui.same_line(0.0);
+                show_help_marker(ui, "Turn gray levels into cell densities");
+                ui.checkbox(im_str!("Invert"), &mut settings.invert);
+
+                let scales = BitmapScale::all();
+                let mut current = scales
+                    .iter()
+                    .position(|scale| *scale == settings.scale)
+                    .unwrap_or(0);
+                ui.set_next_item_width(200.0);
+                if ComboBox::new(im_str!("Scale")).build_simple(
+                    ui,
+                    &mut current,
+                    &scales,
+                    &|scale: &BitmapScale| Cow::Owned(ImString::new(scale.to_string())),
+                ) {
+                    settings.scale = scales[current];
+                }
+                if game_state.rule.species_count() > 1 {
+                    ui.text_disabled(im_str!("Colors pick the species of live cells"));
+                }
+                ui.separator();
+
+                if ui.button(im_str!("Import"), [0.0, 0.0]) {
+                    *import_status = match BitmapImport::open(import_path.to_str()) {
+                        Ok(image) => {
+                            history.record("Import image", game_state);
+                            game_state.import_bitmap(&image, settings);
+                            format!("Imported {}x{} image", image.width(), image.height())
+                        }
+                        Err(e) => format!("Import failed: {}", e),
+                    };
+                }
+                if !import_status.is_empty() {
+                    ui.text_disabled(&im_str!("{}", import_status));
+                }
+            });
+    }
+}
+
+fn draw_export_png_window(
+    ui: &Ui,
+    ctx: &mut Context,
+    game_state: &mut GameState,
+    ui_state: &mut UiState,
+) {
+    let (win_w, win_h) = graphics::size(ctx);
+    // Whole pixels per cell, as on screen
+    let zoom = (ui_state.zoom.round() as usize).max(1);
+
+    if ui_state.show_export_png {
+        let screen_scale = &mut ui_state.png_screen_scale;
+        let grid_lines = &mut ui_state.png_grid_lines;
+        let transparent = &mut ui_state.png_transparent;
+        let png_status = &mut ui_state.png_status;
+        Window::new(im_str!("Export PNG"))
+            .position([win_w / 2.0, win_h / 2.0], Condition::Appearing)
+            .position_pivot([0.5, 0.5])
+            .opened(&mut ui_state.show_export_png)
+            .collapsible(true)
+            .build(ui, || {
+                let (width, height) = game_state.grid_size();
+
+                ui.radio_button(im_str!("1 pixel per cell"), screen_scale, false);
+                ui.same_line(0.0);
+                ui.radio_button(im_str!("Screen scale"), screen_scale, true);
+                ui.checkbox(im_str!("Grid lines"), grid_lines);
+                ui.same_line(0.0);
+                show_help_marker(ui, "Only drawn for cells of 3 pixels or more");
+                ui.checkbox(im_str!("Transparent dead cells"), transparent);
+
+                let cell_size = if *screen_scale { (zoom, zoom) } else { (1, 1) };
+                ui.text(im_str!(
+                    " Image size: {}x{}",
+                    width * cell_size.0,
+                    height * cell_size.1
+                ));
+                ui.separator();
+
+                if ui.button(im_str!("Export"), [0.0, 0.0]) {
+                    let options = PngOptions {
+                        cell_size,
+                        grid_lines: *grid_lines,
+                        transparent: *transparent,
+                    };
+                    let path = export_path(PNG_FILE);
+                    *png_status =
+                        match ImageRenderer::snapshot(game_state, &options).save_png(&path) {
+                            Ok(()) => format!("Exported to {}", path.display()),
+                            Err(e) => format!("Export failed: {}", e),
+                        };
+                }
+                if !png_status.is_empty() {
+                    ui.text_disabled(&im_str!("{}", png_status));
+                }
+            });
+    }
+}
+
+fn draw_recording_window(
+    ui: &Ui,
+    ctx: &mut Context,
+    ui_state: &mut UiState,
+    recording: &mut Option<Recording>,
+) {
+    let (win_w, win_h) = graphics::size(ctx);
+
+    if ui_state.show_recording {
+        let gif = &mut ui_state.recording_gif;
+        let cell_size = &mut ui_state.recording_cell_size;
+        let stride = &mut ui_state.recording_stride;
+        let delay = &mut ui_state.recording_delay;
+        let crop = &mut ui_state.recording_crop;
+        let recording_status = &mut ui_state.recording_status;
+        Window::new(im_str!("Record animation"))
+            .position([win_w / 2.0, win_h - 50.0], Condition::Appearing)
+            .position_pivot([0.5, 1.0])
+            .opened(&mut ui_state.show_recording)
+            .collapsible(true)
+            .build(ui, || {
+                let (file, format) = if *gif {
+                    (RECORDING_GIF_FILE, "GIF")
+                } else {
+                    (RECORDING_APNG_FILE, "APNG")
+                };
+
+                if let Some(current) = recording {
+                    ui.text(im_str!(" Recording as {}", format));
+                    ui.text(im_str!(
+                        " {} frames ({:.1} MB)",
+                        current.len(),
+                        current.memory_used() as f32 / 1024.0 / 1024.0
+                    ));
+                    if current.is_full() {
+                        ui.text_disabled(&im_str!(
+                            "Memory limit reached ({} MB)",
+                            RECORDING_MEMORY_BUDGET / 1024 / 1024
+                        ));
+                    }
+                    ui.separator();
+
+                    if ui.button(im_str!("Stop and save"), [0.0, 0.0]) {
+                        let path = export_path(file);
+                        *recording_status = match current.save(&path) {
+                            Ok(()) => {
+                                format!("Saved {} frames to {}", current.len(), path.display())
+                            }
+                            Err(e) => format!("Recording failed: {}", e),
+                        };
+                        *recording = None;
+                    }
+                    ui.same_line(0.0);
+                    if ui.button(im_str!("Cancel"), [0.0, 0.0]) {
+                        *recording = None;
+                    }
+                    return;
+                }
+
+                ui.radio_button(im_str!("GIF"), gif, true);
+                ui.same_line(0.0);
+                ui.radio_button(im_str!("APNG"), gif, false);
+                ui.set_next_item_width(100.0);
+                ui.input_int(im_str!("Cell size"), cell_size).build();
+                ui.set_next_item_width(100.0);
+                ui.input_int(im_str!("Stride"), stride).build();
+                ui.same_line(0.0);
+                show_help_marker(ui, "Generations between frames");
+                ui.set_next_item_width(100.0);
+                ui.input_int(im_str!("Frame delay (ms)"), delay).build();
+                ui.checkbox(im_str!("Crop to pattern"), crop);
+                ui.separator();
+
+                if ui.button(im_str!("Start recording"), [0.0, 0.0]) {
+                    *recording = Some(Recording::new(RecordingOptions {
+                        cell_size: (*cell_size).max(1) as usize,
+                        stride: (*stride).max(1) as usize,
+                        delay: (*delay).clamp(0, i32::from(u16::MAX)) as u16,
+                        crop: *crop,
+                    }));
+                    recording_status.clear();
+                }
+                if !recording_status.is_empty() {
+                    ui.text_disabled(&im_str!("{}", recording_status));
+                }
+            });
+    }
+}
+
+fn draw_motion(ui: &Ui, motion: Option<Motion>) {
+    match motion {
+        Some(motion) => {
+            ui.text(im_str!(" Period: {}", motion.period));
+            ui.text(im_str!(
+                " Displacement: ({}, {})",
+                motion.displacement.0,
+                motion.displacement.1
+            ));
+            ui.text(im_str!(" Speed: {}", motion));
+            if motion.is_moving() {
+                ui.text(im_str!(" Heading: {}", motion.heading()));
+            }
+        }
+        None => ui.text(im_str!(" Period: not detected")),
+    }
+}
+
+fn draw_motion_window(
+    ui: &Ui,
+    ctx: &mut Context,
+    game_state: &mut GameState,
+    ui_state: &mut UiState,
+    tracked: &mut Option<TrackedObject>,
+) {
+    let (_win_w, win_h) = graphics::size(ctx);
+
+    if ui_state.show_motion {
+        let track_object = &mut ui_state.track_object;
+        let picking_object = &mut ui_state.picking_object;
+        Window::new(im_str!("Spaceship detector"))
+            .position([50.0, win_h / 2.0], Condition::Appearing)
+            .position_pivot([0.0, 0.5])
+            .opened(&mut ui_state.show_motion)
+            .collapsible(true)
+            .build(ui, || {
+                ui.radio_button(im_str!("Whole pattern"), track_object, false);
+                ui.same_line(0.0);
+                ui.radio_button(im_str!("Selected object"), track_object, true);
+                ui.separator();
+
+                if !*track_object {
+                    let motion = game_state.stats.periodicity.map(|periodicity| {
+                        Motion::from_periodicity(periodicity, game_state.grid_size())
+                    });
+                    draw_motion(ui, motion);
+                    return;
+                }
+
+                if *picking_object {
+                    ui.text_disabled(im_str!("Click on an object in the grid"));
+                } else if ui.button(im_str!("Pick object"), [0.0, 0.0]) {
+                    *picking_object = true;
+                }
+                ui.same_line(0.0);
+                show_help_marker(
+                    ui,
+                    "The object is run in isolation, which is only supported for the Game of Life",
+                );
+
+                match tracked {
+                    Some(object) => {
+                        ui.text(im_str!(
+                            " {} cells, selected at generation {}",
+                            object.pattern.population(),
+                            object.generation
+                        ));
+                        match object.analyzing() {
+                            Some(generations) => ui.text_disabled(im_str!(
+                                " Analyzing, {} generations run",
+                                generations
+                            )),
+                            None => draw_motion(ui, object.motion),
+                        }
+                    }
+                    None => ui.text(im_str!(" No object selected")),
+                }
             });
     }
 }
@@ -109,10 +1198,22 @@ fn draw_help_window(
             .movable(false)
             .resizable(false)
             .collapsible(true)
-            .build(&ui, || {
+            .build(ui, || {
                 ui.text(im_str!("Mouse left-click to draw cells"));
                 ui.text(im_str!("Mouse right-click to erase cells"));
                 ui.text(im_str!("Mouse wheel to change draw size"));
+                ui.text(im_str!("CTRL+mouse wheel to zoom"));
+                ui.text(im_str!("Middle-click and drag to pan"));
+                ui.text(im_str!("F to zoom to fit"));
+                ui.text(im_str!("3D rules draw on the current Z slice"));
+                ui.text(im_str!("Space to pause or resume"));
+                ui.text(im_str!("S or Right to step one generation"));
+                ui.text(im_str!("N to step N generations"));
+                ui.text(im_str!("G to run until the target generation"));
+                ui.text(im_str!("+ and - to change speed"));
+                ui.text(im_str!("Return to randomize"));
+                ui.text(im_str!("Backspace to step back (reversible rules)"));
+                ui.text(im_str!("CTRL+Z to undo, CTRL+Y to redo"));
             });
     }
 }
@@ -133,7 +1234,7 @@ fn draw_about_window(
             .movable(false)
             .resizable(false)
             .collapsible(false)
-            .build(&ui, || {
+            .build(ui, || {
                 ui.text(im_str!("caw - cellular automata workspace"));
                 ui.text(im_str!("version {}", env!("CARGO_PKG_VERSION")));
                 ui.separator();
@@ -144,9 +1245,34 @@ fn draw_about_window(
     }
 }
 
-pub fn render_ui(ui: &Ui, ctx: &mut Context, game_state: &mut GameState, ui_state: &mut UiState) {
-    draw_main_menu(ui, ctx, game_state, ui_state);
-    draw_settings_window(ui, ctx, game_state, ui_state);
+pub fn render_ui(
+    ui: &Ui,
+    ctx: &mut Context,
+    game_state: &mut GameState,
+    ui_state: &mut UiState,
+    workspace: &mut Workspace,
+    recording: &mut Option<Recording>,
+) {
+    let Workspace {
+        history,
+        timeline,
+        census,
+        population,
+        tracked,
+        ..
+    } = workspace;
+
+    draw_main_menu(ui, ctx, game_state, ui_state, history);
+    draw_settings_window(ui, ctx, game_state, ui_state, history);
+    draw_timeline_window(ui, ctx, game_state, ui_state, history, timeline);
+    draw_census_window(ui, ctx, game_state, ui_state, history, census);
+    draw_population_window(ui, ctx, game_state, ui_state, population);
+    draw_stats_window(ui, ctx, game_state, ui_state);
+    draw_motion_window(ui, ctx, game_state, ui_state, tracked);
+    draw_grid_size_window(ui, ctx, game_state, ui_state, history);
+    draw_import_image_window(ui, ctx, game_state, ui_state, history);
+    draw_export_png_window(ui, ctx, game_state, ui_state);
+    draw_recording_window(ui, ctx, ui_state, recording);
     draw_help_window(ui, ctx, game_state, ui_state);
     draw_about_window(ui, ctx, game_state, ui_state);
 }
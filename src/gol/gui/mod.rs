@@ -3,5 +3,5 @@ mod elements;
 mod state;
 
 pub use self::compat::ImGuiWrapper;
-pub use self::elements::render_ui;
+pub use self::elements::{render_ui, MAX_GRID_SIDE};
 pub use self::state::UiState;
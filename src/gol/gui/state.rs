@@ -1,11 +1,86 @@
 // State
 
+use imgui::ImString;
+
+use super::super::logic::{Anchor, BitmapImport};
+
 #[derive(Clone)]
 pub struct UiState {
     pub show_window: bool,
     pub show_help: bool,
     pub show_about: bool,
+    pub show_timeline: bool,
+    /// Set while the timeline slider is dragged
+    pub seeking: bool,
+    pub show_census: bool,
+    pub show_population: bool,
+    pub show_stats: bool,
+    pub show_motion: bool,
+    pub show_export_png: bool,
+    /// Export PNG images at the screen scale instead of 1 pixel per cell
+    pub png_screen_scale: bool,
+    pub png_grid_lines: bool,
+    pub png_transparent: bool,
+    /// Result of the last PNG export
+    pub png_status: String,
+    pub show_recording: bool,
+    /// Record GIF files instead of APNG
+    pub recording_gif: bool,
+    /// Cell size in recorded frames, in pixels
+    pub recording_cell_size: i32,
+    /// Generations between recorded frames
+    pub recording_stride: i32,
+    /// Delay between recorded frames, in milliseconds
+    pub recording_delay: i32,
+    pub recording_crop: bool,
+    /// Result of the last recording
+    pub recording_status: String,
+    pub show_import_image: bool,
+    /// Path of the image to import
+    pub import_path: ImString,
+    pub bitmap_import: BitmapImport,
+    /// Result of the last image import
+    pub import_status: String,
+    /// Analyze the selected object instead of the whole pattern
+    pub track_object: bool,
+    /// Next click on the grid selects an object
+    pub picking_object: bool,
+    /// Result of the last census export
+    pub census_status: String,
+    /// Result of the last population export
+    pub population_status: String,
+    /// Number of samples shown in population plots
+    pub plot_samples: i32,
+    /// Camera zoom, in pixels per cell
+    pub zoom: f32,
+    pub show_grid_size: bool,
+    /// Grid size to apply, as `[width, height]`
+    pub grid_size: [i32; 2],
+    pub grid_anchor: Anchor,
+    /// Pixels per cell, applied with the grid size and when following the
+    /// window size
+    pub cell_scale: f32,
+    /// Resize the grid with the window
+    pub grid_follows_window: bool,
+    /// Set to apply the cell scale to the camera on the next frame
+    pub apply_cell_scale: bool,
+    /// Result of the last grid resize
+    pub grid_size_status: String,
+    /// Set to show the whole grid on the next frame
+    pub zoom_to_fit: bool,
     pub cursor_size: usize,
+    pub brush_species: u8,
+    pub step_count: i32,
+    pub target_generation: i32,
+    pub volume_rule: ImString,
+    /// apgcode of the object to paste
+    pub apgcode: ImString,
+    /// Block sizes used for spatial entropy, as a comma-separated list
+    pub entropy_block_sizes: ImString,
+    /// Seed of the soup to replay
+    pub soup_seed: ImString,
+    /// Soup region, as `[x, y, width, height]`
+    pub soup_region: [i32; 4],
 }
 
 impl UiState {
@@ -14,7 +89,63 @@ impl UiState {
             show_window: true,
             show_help: true,
             show_about: false,
+            show_timeline: false,
+            seeking: false,
+            show_census: false,
+            show_population: false,
+            show_stats: false,
+            show_motion: false,
+            show_export_png: false,
+            png_screen_scale: false,
+            png_grid_lines: false,
+            png_transparent: false,
+            png_status: String::new(),
+            show_recording: false,
+            recording_gif: true,
+            recording_cell_size: 4,
+            recording_stride: 1,
+            recording_delay: 100,
+            recording_crop: true,
+            recording_status: String::new(),
+            show_import_image: false,
+            import_path: {
+                let mut path = ImString::new("image.png");
+                path.reserve(256);
+                path
+            },
+            bitmap_import: BitmapImport::default(),
+            import_status: String::new(),
+            track_object: false,
+            picking_object: false,
+            census_status: String::new(),
+            population_status: String::new(),
+            plot_samples: 500,
+            zoom: 1.0,
+            show_grid_size: false,
+            grid_size: [0, 0],
+            grid_anchor: Anchor::Center,
+            cell_scale: 1.0,
+            grid_follows_window: false,
+            apply_cell_scale: false,
+            grid_size_status: String::new(),
+            zoom_to_fit: false,
             cursor_size: 10,
+            brush_species: 0,
+            step_count: 10,
+            target_generation: 1000,
+            volume_rule: {
+                let mut rule = ImString::new("4/4/5/M");
+                rule.reserve(32);
+                rule
+            },
+            apgcode: ImString::with_capacity(256),
+            entropy_block_sizes: {
+                let mut sizes = ImString::new("2,4,8");
+                sizes.reserve(32);
+                sizes
+            },
+            soup_seed: ImString::with_capacity(32),
+            soup_region: [0, 0, 16, 16],
         }
     }
 }
@@ -0,0 +1,371 @@
+//! Headless runner
+
+use std::fs;
+use std::thread;
+use std::time::Duration;
+
+use super::logic::{BitmapImport, BitmapScale, GameState, GameStats, Pattern, PatternFile, Rule};
+use super::renderer::{
+    ConsoleMode, ConsoleRenderer, ImageRenderer, PngOptions, Recording, RecordingOptions, Renderer,
+};
+
+const USAGE: &str = "Usage: caw run [options]
+
+Options:
+    --pattern <file>        Load a RLE or plaintext pattern at the grid center
+    --apgcode <code>        Load an object from its apgcode at the grid center
+    --image <file>          Load an image, with dark pixels as live cells
+    --threshold <level>     Image luminance threshold, from 0 to 1 (default: 0.5)
+    --dither                Dither the image, turning gray levels into densities
+    --invert                Use light image pixels as live cells
+    --image-scale <scale>   Image scaling: fit, stretch or original (default: fit)
+    --rule <name>           Rule name or notation (default: B3/S23, or the pattern rule)
+    --seed <seed>           Soup seed, when no pattern is given (default: random)
+    --density <density>     Soup density, from 0 to 1 (default: 0.5)
+    --size <width>x<height> Grid size (default: 64x64)
+    --generations <count>   Generations to run (default: 100)
+    --stats                 Print final stats (default when no output is requested)
+    --output <file>         Write the final pattern to a RLE file
+    --png <file>            Write the final grid to a PNG image
+    --png-scale <pixels>    Cell size in the PNG image (default: 1)
+    --grid-lines            Draw grid lines in the PNG image, for cells of 3 pixels or more
+    --transparent           Leave dead cells transparent in the PNG image
+    --record <file>         Record the run to an animated GIF (.gif) or APNG (.png) file
+    --record-stride <count> Generations between recorded frames (default: 1)
+    --record-scale <pixels> Cell size in recorded frames (default: 4)
+    --frame-delay <ms>      Delay between recorded frames (default: 100)
+    --crop                  Crop recorded frames to the pattern bounding box
+    --console               Stream the grid to the terminal
+    --delay <ms>            Delay between streamed generations (default: 50)
+    --mode <mode>           Cells per character: full, half or braille (default: full)
+    --color                 Color streamed cells by age, needs a 24-bit color terminal";
+
+struct RunArgs {
+    pattern: Option<String>,
+    apgcode: Option<String>,
+    image: Option<String>,
+    bitmap_import: BitmapImport,
+    rule: Option<String>,
+    seed: Option<u64>,
+    density: Option<f32>,
+    size: (usize, usize),
+    generations: usize,
+    stats: bool,
+    output: Option<String>,
+    png: Option<String>,
+    png_options: PngOptions,
+    record: Option<String>,
+    record_options: RecordingOptions,
+    console: bool,
+    delay: u64,
+    mode: ConsoleMode,
+    color: bool,
+}
+
+fn parse_args(args: &[String]) -> Result<RunArgs, String> {
+    let mut parsed = RunArgs {
+        pattern: None,
+        apgcode: None,
+        image: None,
+        bitmap_import: BitmapImport::default(),
+        rule: None,
+        seed: None,
+        density: None,
+        size: (64, 64),
+        generations: 100,
+        stats: false,
+        output: None,
+        png: None,
+        png_options: PngOptions {
+            cell_size: (1, 1),
+            grid_lines: false,
+            transparent: false,
+        },
+        record: None,
+        record_options: RecordingOptions {
+            cell_size: 4,
+            stride: 1,
+            delay: 100,
+            crop: false,
+        },
+        console: false,
+        delay: 50,
+        mode: ConsoleMode::Full,
+        color: false,
+    };
+
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        let mut value = || {
+            args.next()
+                .cloned()
+                .ok_or_else(|| format!("Missing value for {}", arg))
+        };
+        let invalid = |value: &str| format!("Invalid value for {}: {}", arg, value);
+
+        match arg.as_str() {
+            "--pattern" => parsed.pattern = Some(value()?),
+            "--apgcode" => parsed.apgcode = Some(value()?),
+            "--image" => parsed.image = Some(value()?),
+            "--threshold" => {
+                let value = value()?;
+                parsed.bitmap_import.threshold = value.parse().map_err(|_| invalid(&value))?;
+            }
+            "--dither" => parsed.bitmap_import.dither = true,
+            "--invert" => parsed.bitmap_import.invert = true,
+            "--image-scale" => {
+                let value = value()?;
+                parsed.bitmap_import.scale =
+                    BitmapScale::from_name(&value).ok_or_else(|| invalid(&value))?;
+            }
+            "--rule" => parsed.rule = Some(value()?),
+            "--seed" => {
+                let value = value()?;
+                parsed.seed = Some(value.parse().map_err(|_| invalid(&value))?);
+            }
+            "--density" => {
+                let value = value()?;
+                parsed.density = Some(value.parse().map_err(|_| invalid(&value))?);
+            }
+            "--size" => {
+                let value = value()?;
+                parsed.size = value
+                    .split_once('x')
+                    .and_then(|(w, h)| Some((w.parse().ok()?, h.parse().ok()?)))
+                    .filter(|(w, h)| *w > 0 && *h > 0)
+                    .ok_or_else(|| invalid(&value))?;
+            }
+            "--generations" => {
+                let value = value()?;
+                parsed.generations = value.parse().map_err(|_| invalid(&value))?;
+            }
+            "--delay" => {
+                let value = value()?;
+                parsed.delay = value.parse().map_err(|_| invalid(&value))?;
+            }
+            "--stats" => parsed.stats = true,
+            "--output" => parsed.output = Some(value()?),
+            "--png" => parsed.png = Some(value()?),
+            "--png-scale" => {
+                let value = value()?;
+                let scale = value
+                    .parse()
+                    .ok()
+                    .filter(|scale| *scale > 0)
+                    .ok_or_else(|| invalid(&value))?;
+                parsed.png_options.cell_size = (scale, scale);
+            }
+            "--grid-lines" => parsed.png_options.grid_lines = true,
+            "--transparent" => parsed.png_options.transparent = true,
+            "--record" => parsed.record = Some(value()?),
+            "--record-stride" => {
+                let value = value()?;
+                parsed.record_options.stride = value
+                    .parse()
+                    .ok()
+                    .filter(|stride| *stride > 0)
+                    .ok_or_else(|| invalid(&value))?;
+            }
+            "--record-scale" => {
+                let value = value()?;
+                parsed.record_options.cell_size = value
+                    .parse()
+                    .ok()
+                    .filter(|scale| *scale > 0)
+                    .ok_or_else(|| invalid(&value))?;
+            }
+            "--frame-delay" => {
+                let value = value()?;
+                parsed.record_options.delay = value.parse().map_err(|_| invalid(&value))?;
+            }
+            "--crop" => parsed.record_options.crop = true,
+            "--console" => parsed.console = true,
+            "--mode" => {
+                let value = value()?;
+                parsed.mode = ConsoleMode::from_name(&value).ok_or_else(|| invalid(&value))?;
+            }
+            "--color" => parsed.color = true,
+            "-h" | "--help" => return Err(String::new()),
+            _ => return Err(format!("Unknown option: {}", arg)),
+        }
+    }
+
+    if parsed.output.is_none() && parsed.png.is_none() && parsed.record.is_none() && !parsed.console
+    {
+        parsed.stats = true;
+    }
+
+    Ok(parsed)
+}
+
+fn print_stats(state: &GameState) {
+    let stats = &state.stats;
+    println!("rule: {}", state.rule.notation());
+    println!("generation: {}", state.current_tick);
+    println!("population: {}", stats.population);
+    println!("births: {}", stats.births);
+    println!("deaths: {}", stats.deaths);
+    println!("density: {:.4}", stats.density);
+    match stats.bounding_box {
+        Some((min_x, min_y, max_x, max_y)) => println!(
+            "bounding_box: {} {} {} {}",
+            min_x,
+            min_y,
+            max_x - min_x + 1,
+            max_y - min_y + 1
+        ),
+        None => println!("bounding_box: none"),
+    }
+    match stats.periodicity {
+        Some(p) => println!(
+            "periodicity: period {} since {}, displacement {} {}",
+            p.period, p.start, p.displacement.0, p.displacement.1
+        ),
+        None => println!("periodicity: none"),
+    }
+    if state.rule == Rule::Life {
+        println!(
+            "apgcode: {}",
+            state.apgcode().unwrap_or_else(|| "none".to_string())
+        );
+    }
+}
+
+fn setup_state(args: &RunArgs) -> Result<GameState, String> {
+    let mut state = GameState::new(args.size);
+    // Periodicity is only printed with the stats
+    state.detect_cycles = args.stats;
+
+    let file = match &args.pattern {
+        Some(path) => {
+            let contents =
+                fs::read_to_string(path).map_err(|e| format!("Could not read {}: {}", path, e))?;
+            Some(PatternFile::parse(&contents).map_err(|e| format!("{}: {}", path, e))?)
+        }
+        None => None,
+    };
+
+    let rule_name = args
+        .rule
+        .as_ref()
+        .or_else(|| file.as_ref().and_then(|file| file.rule.as_ref()));
+    if let Some(name) = rule_name {
+        state.set_rule(Rule::from_name(name).ok_or_else(|| format!("Unknown rule: {}", name))?);
+    }
+
+    if let Some(density) = args.density {
+        state.soup.density = density;
+    }
+
+    // The rule may have changed the grid size
+    let (width, height) = state.grid_size();
+    let center = (width / 2, height / 2);
+
+    if let Some(path) = &args.image {
+        let image = BitmapImport::open(path).map_err(|e| format!("{}: {}", path, e))?;
+        state.import_bitmap(&image, &args.bitmap_import);
+    }
+    if let Some(file) = &file {
+        state.paste_pattern(&file.pattern, center);
+    }
+    if let Some(code) = &args.apgcode {
+        if !state.paste_apgcode(code, center) {
+            return Err(format!("Invalid apgcode: {}", code));
+        }
+    }
+    if file.is_none() && args.apgcode.is_none() && args.image.is_none() {
+        match args.seed {
+            Some(seed) => state.randomize_with_seed(seed),
+            None => state.randomize(),
+        }
+        eprintln!("seed: {}", state.soup.seed);
+    }
+
+    state.stats = GameStats::from_state(&state);
+    Ok(state)
+}
+
+/// Run a simulation from command line arguments, returning the exit code
+pub fn run_headless(args: &[String]) -> i32 {
+    let args = match parse_args(args) {
+        Ok(args) => args,
+        Err(e) if e.is_empty() => {
+            println!("{}", USAGE);
+            return 0;
+        }
+        Err(e) => {
+            eprintln!("{}\n\n{}", e, USAGE);
+            return 1;
+        }
+    };
+
+    let mut state = match setup_state(&args) {
+        Ok(state) => state,
+        Err(e) => {
+            eprintln!("{}", e);
+            return 1;
+        }
+    };
+    let mut console = ConsoleRenderer::new();
+    console.mode = args.mode;
+    console.color = args.color;
+    let mut recording = args
+        .record
+        .as_ref()
+        .map(|_| Recording::new(args.record_options));
+    if args.console {
+        console.render(&state);
+    }
+    if let Some(recording) = &mut recording {
+        recording.record(&state);
+    }
+    for _ in 0..args.generations {
+        state.tick();
+        if args.console {
+            thread::sleep(Duration::from_millis(args.delay));
+            console.render(&state);
+        }
+        if let Some(recording) = &mut recording {
+            recording.record(&state);
+        }
+    }
+
+    if args.console {
+        eprintln!(
+            "console: {} bytes per frame on average",
+            console.average_frame_bytes()
+        );
+    }
+    if args.stats {
+        print_stats(&state);
+    }
+
+    if let Some(path) = &args.output {
+        let pattern = Pattern::from_state(&state);
+        let rle = PatternFile::to_rle(&pattern, &state.rule.notation());
+        if let Err(e) = fs::write(path, rle) {
+            eprintln!("Could not write {}: {}", path, e);
+            return 2;
+        }
+    }
+
+    if let (Some(path), Some(recording)) = (&args.record, &recording) {
+        if recording.is_full() {
+            eprintln!("Recording stopped after {} frames", recording.len());
+        }
+        if let Err(e) = recording.save(path) {
+            eprintln!("Could not write {}: {}", path, e);
+            return 2;
+        }
+    }
+
+    if let Some(path) = &args.png {
+        let image = ImageRenderer::snapshot(&state, &args.png_options);
+        if let Err(e) = image.save_png(path) {
+            eprintln!("Could not write {}: {}", path, e);
+            return 2;
+        }
+    }
+
+    0
+}
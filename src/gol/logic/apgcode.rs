@@ -0,0 +1,393 @@
+//! apgcode object identification
+//!
+//! Objects are identified by their apgcode, as used by apgsearch and public
+//! catalogues: a prefix giving the object kind (`xs` still life with its
+//! population, `xp` oscillator or `xq` spaceship with their period), then
+//! the extended Wechsler encoding of its canonical phase and orientation.
+//!
+//! The encoding splits the pattern in strips of 5 rows, separated by `z`.
+//! Each column of a strip is a digit from `0` to `v`, with its top cell as
+//! the lowest bit. Runs of empty columns are shortened to `w` (2), `x` (3)
+//! and `y` followed by a digit (4 to 39).
+
+use super::{GameState, Pattern};
+
+/// Maximum period searched when identifying objects
+pub const MAX_PERIOD: usize = 128;
+
+const DIGITS: &[u8] = b"0123456789abcdefghijklmnopqrstuvwxyz";
+
+/// Encode a pattern with the extended Wechsler format
+pub fn encode_wechsler(pattern: &Pattern) -> String {
+    let pattern = pattern.normalize();
+    let (width, height) = pattern.size();
+    let mut strips = vec![];
+
+    for strip in 0..height.div_ceil(5) {
+        let mut columns = (0..width)
+            .map(|x| {
+                (0..5).fold(0, |acc, row| {
+                    if pattern.contains((x as i32, (strip * 5 + row) as i32)) {
+                        acc | 1 << row
+                    } else {
+                        acc
+                    }
+                })
+            })
+            .collect::<Vec<usize>>();
+
+        while columns.last() == Some(&0) {
+            columns.pop();
+        }
+
+        strips.push(encode_strip(&columns));
+    }
+
+    strips.join("z")
+}
+
+fn encode_strip(columns: &[usize]) -> String {
+    let mut output = String::new();
+    let mut zeros = 0;
+
+    let flush = |output: &mut String, zeros: &mut usize| {
+        while *zeros > 0 {
+            match *zeros {
+                1 => {
+                    output.push('0');
+                    *zeros = 0;
+                }
+                2 => {
+                    output.push('w');
+                    *zeros = 0;
+                }
+                3 => {
+                    output.push('x');
+                    *zeros = 0;
+                }
+                n => {
+                    let run = n.min(39);
+                    output.push('y');
+                    output.push(DIGITS[run - 4] as char);
+                    *zeros -= run;
+                }
+            }
+        }
+    };
+
+    for column in columns {
+        if *column == 0 {
+            zeros += 1;
+        } else {
+            flush(&mut output, &mut zeros);
+            output.push(DIGITS[*column] as char);
+        }
+    }
+
+    output
+}
+
+/// Decode a pattern from the extended Wechsler format
+pub fn decode_wechsler(code: &str) -> Option<Pattern> {
+    let mut cells = vec![];
+    let mut chars = code.chars();
+    let (mut x, mut strip) = (0usize, 0usize);
+
+    while let Some(c) = chars.next() {
+        match c {
+            'w' => x += 2,
+            'x' => x += 3,
+            'y' => x += 4 + digit_value(chars.next()?)?,
+            'z' => {
+                x = 0;
+                strip += 1;
+            }
+            c => {
+                let column = digit_value(c).filter(|v| *v < 32)?;
+                for row in 0..5 {
+                    if column & (1 << row) != 0 {
+                        cells.push((x as i32, (strip * 5 + row) as i32));
+                    }
+                }
+                x += 1;
+            }
+        }
+    }
+
+    Some(Pattern::new(cells))
+}
+
+fn digit_value(c: char) -> Option<usize> {
+    DIGITS.iter().position(|d| *d as char == c)
+}
+
+/// Decode a pattern from an apgcode, like `xs4_33` or `xq4_153`
+pub fn parse_apgcode(code: &str) -> Option<Pattern> {
+    let code = code.trim();
+    let (prefix, wechsler) = code.split_once('_')?;
+
+    let kind_ok = ["xs", "xp", "xq"].iter().any(|kind| {
+        prefix.starts_with(kind)
+            && prefix.len() > 2
+            && prefix[2..].chars().all(|c| c.is_ascii_digit())
+    });
+    if !kind_ok {
+        return None;
+    }
+
+    decode_wechsler(wechsler).filter(|pattern| !pattern.is_empty())
+}
+
+/// Compare encodings: shortest first, then alphabetical
+fn is_better(candidate: &str, best: &Option<String>) -> bool {
+    match best {
+        Some(best) => (candidate.len(), candidate) < (best.len(), best.as_str()),
+        None => true,
+    }
+}
+
+/// Get the canonical Wechsler encoding among all orientations of the phases
+pub fn canonical_wechsler(phases: &[Pattern]) -> String {
+    let mut best = None;
+
+    for phase in phases {
+        for pattern in phase.transforms() {
+            let code = encode_wechsler(&pattern);
+            if is_better(&code, &best) {
+                best = Some(code);
+            }
+        }
+    }
+
+    best.unwrap_or_default()
+}
+
+/// Identify an isolated object, returning its apgcode
+pub fn apgcode(pattern: &Pattern) -> Option<String> {
+    if pattern.is_empty() {
+        return None;
+    }
+
+    let (period, displacement) = pattern.find_period(MAX_PERIOD)?;
+    let mut phases = vec![pattern.clone()];
+    for _ in 1..period {
+        let next = phases[phases.len() - 1].step();
+        phases.push(next);
+    }
+
+    let prefix = if displacement != (0, 0) {
+        format!("xq{}", period)
+    } else if period == 1 {
+        format!("xs{}", pattern.population())
+    } else {
+        format!("xp{}", period)
+    };
+
+    Some(format!("{}_{}", prefix, canonical_wechsler(&phases)))
+}
+
+impl GameState {
+    /// Identify the whole grid as one object
+    pub fn apgcode(&self) -> Option<String> {
+        let mut components = Pattern::components(self);
+        let pattern = if components.len() == 1 {
+            components.remove(0)
+        } else {
+            Pattern::from_state(self)
+        };
+
+        apgcode(&pattern)
+    }
+
+    /// Paste an object from its apgcode, centered on a position.
+    ///
+    /// Returns `false` if the code is invalid.
+    pub fn paste_apgcode(&mut self, code: &str, pos: (usize, usize)) -> bool {
+        match parse_apgcode(code) {
+            Some(pattern) => {
+                self.paste_pattern(&pattern, pos);
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+/// Get the usual name of common objects
+pub fn common_name(apgcode: &str) -> Option<&'static str> {
+    let name = match apgcode {
+        "xs4_33" => "block",
+        "xs6_696" => "beehive",
+        "xs7_2596" => "loaf",
+        "xs5_253" => "boat",
+        "xs6_356" => "ship",
+        "xs4_252" => "tub",
+        "xs8_6996" => "pond",
+        "xs6_25a4" => "barge",
+        "xs7_178c" => "eater",
+        "xs7_25ac" => "long boat",
+        "xs8_69ic" => "mango",
+        "xs8_25ak8" => "long barge",
+        "xs12_g8o653z11" => "ship-tie",
+        "xs9_31ego" => "integral sign",
+        "xs14_g88m952z121" => "half-bakery",
+        "xp2_7" => "blinker",
+        "xp2_7e" => "toad",
+        "xp2_318c" => "beacon",
+        "xp3_co9nas0san9oczgoldlo0oldlogz1047210127401" => "pulsar",
+        "xp15_4r4z4r4" => "pentadecathlon",
+        "xq4_153" => "glider",
+        "xq4_6frc" => "lightweight spaceship",
+        "xq4_27dee6" => "middleweight spaceship",
+        "xq4_27deee6" => "heavyweight spaceship",
+        _ => return None,
+    };
+
+    Some(name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pattern(rows: &[&str]) -> Pattern {
+        let mut cells = vec![];
+        for (y, row) in rows.iter().enumerate() {
+            for (x, c) in row.chars().enumerate() {
+                if c == 'o' {
+                    cells.push((x as i32, y as i32));
+                }
+            }
+        }
+        Pattern::new(cells)
+    }
+
+    #[test]
+    fn test_encode_wechsler() {
+        assert_eq!(encode_wechsler(&pattern(&["oo", "oo"])), "33");
+        assert_eq!(encode_wechsler(&pattern(&["o.....o"])), "1y11");
+        assert_eq!(
+            encode_wechsler(&pattern(&["o", ".", ".", ".", ".", "o"])),
+            "1z1"
+        );
+        assert_eq!(encode_wechsler(&pattern(&["o...o"])), "1x1");
+    }
+
+    #[test]
+    fn test_still_lifes() {
+        assert_eq!(apgcode(&pattern(&["oo", "oo"])).unwrap(), "xs4_33");
+        assert_eq!(
+            apgcode(&pattern(&[".oo.", "o..o", ".oo."])).unwrap(),
+            "xs6_696"
+        );
+        assert_eq!(
+            apgcode(&pattern(&["oo.", "o.o", ".o."])).unwrap(),
+            "xs5_253"
+        );
+        assert_eq!(
+            apgcode(&pattern(&[".oo.", "o..o", "o..o", ".oo."])).unwrap(),
+            "xs8_6996"
+        );
+    }
+
+    #[test]
+    fn test_oscillators() {
+        assert_eq!(apgcode(&pattern(&["ooo"])).unwrap(), "xp2_7");
+        assert_eq!(apgcode(&pattern(&[".ooo", "ooo."])).unwrap(), "xp2_7e");
+        assert_eq!(
+            apgcode(&pattern(&["oo..", "oo..", "..oo", "..oo"])).unwrap(),
+            "xp2_318c"
+        );
+    }
+
+    #[test]
+    fn test_spaceships() {
+        assert_eq!(
+            apgcode(&pattern(&[".o.", "..o", "ooo"])).unwrap(),
+            "xq4_153"
+        );
+        assert_eq!(
+            apgcode(&pattern(&[".o..o", "o....", "o...o", "oooo."])).unwrap(),
+            "xq4_6frc"
+        );
+    }
+
+    #[test]
+    fn test_decode_wechsler() {
+        assert_eq!(decode_wechsler("33").unwrap(), pattern(&["oo", "oo"]));
+        assert_eq!(decode_wechsler("1y11").unwrap(), pattern(&["o.....o"]));
+        assert_eq!(
+            decode_wechsler("1z1").unwrap(),
+            pattern(&["o", ".", ".", ".", ".", "o"])
+        );
+        assert!(decode_wechsler("1y").is_none());
+        assert!(decode_wechsler("3!").is_none());
+    }
+
+    #[test]
+    fn test_parse_apgcode() {
+        assert_eq!(parse_apgcode("xs4_33").unwrap().population(), 4);
+        assert!(parse_apgcode("33").is_none());
+        assert!(parse_apgcode("xz4_33").is_none());
+        assert!(parse_apgcode("xs_33").is_none());
+        assert!(parse_apgcode("xs4_0").is_none());
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let codes = [
+            "xs4_33",
+            "xs6_696",
+            "xs7_2596",
+            "xs5_253",
+            "xs6_356",
+            "xs4_252",
+            "xs8_6996",
+            "xs6_25a4",
+            "xs7_178c",
+            "xs7_25ac",
+            "xs8_69ic",
+            "xs8_25ak8",
+            "xs12_g8o653z11",
+            "xs9_31ego",
+            "xs14_g88m952z121",
+            "xp2_7",
+            "xp2_7e",
+            "xp2_318c",
+            "xp3_co9nas0san9oczgoldlo0oldlogz1047210127401",
+            "xp15_4r4z4r4",
+            "xq4_153",
+            "xq4_6frc",
+            "xq4_27dee6",
+            "xq4_27deee6",
+        ];
+
+        for code in codes.iter() {
+            let pattern = parse_apgcode(code).unwrap();
+            assert_eq!(apgcode(&pattern).as_deref(), Some(*code));
+            assert!(common_name(code).is_some(), "{} has no name", code);
+
+            // Any orientation or phase gives the same code
+            let moved = pattern.transform(5).step().translate((7, -3));
+            assert_eq!(apgcode(&moved).as_deref(), Some(*code));
+        }
+    }
+
+    #[test]
+    fn test_game_state() {
+        let mut state = GameState::new((16, 16));
+        assert!(state.paste_apgcode("xq4_153", (0, 0)));
+        assert_eq!(state.apgcode().as_deref(), Some("xq4_153"));
+        state.cycle(10);
+        assert_eq!(state.apgcode().as_deref(), Some("xq4_153"));
+
+        assert!(!state.paste_apgcode("xq4", (8, 8)));
+        assert!(state.paste_apgcode("xs4_33", (8, 8)));
+        assert_eq!(state.apgcode(), None);
+    }
+
+    #[test]
+    fn test_unstable() {
+        assert_eq!(apgcode(&pattern(&[".oo", "oo.", ".o."])), None);
+    }
+}
@@ -0,0 +1,318 @@
+//! Bitmap import
+//!
+//! Images are scaled to the grid, then each pixel becomes a cell: dark
+//! pixels are alive, unless inverted. Floyd–Steinberg dithering spreads the
+//! thresholding error to the neighbor pixels, so gray levels become cell
+//! densities. With multi-species rules, the hue of live pixels picks their
+//! species.
+
+use std::fmt;
+use std::path::Path;
+
+use image::imageops::{self, FilterType};
+use image::{Rgb, RgbImage};
+
+use super::{GameState, GameStats};
+
+/// Default luminance threshold
+pub const DEFAULT_THRESHOLD: f32 = 0.5;
+
+/// Image scaling
+#[derive(Clone, Copy, PartialEq, Debug, Default)]
+pub enum BitmapScale {
+    /// Largest size fitting in the grid, keeping the aspect ratio
+    #[default]
+    Fit,
+    /// Whole grid
+    Stretch,
+    /// One pixel per cell, cropped to the grid
+    Original,
+}
+
+/// Bitmap import settings
+#[derive(Clone, Debug)]
+pub struct BitmapImport {
+    /// Luminance under which pixels are alive, from 0 to 1
+    pub threshold: f32,
+    /// Use Floyd–Steinberg dithering
+    pub dither: bool,
+    /// Make light pixels alive instead of dark ones
+    pub invert: bool,
+    pub scale: BitmapScale,
+}
+
+impl BitmapScale {
+    pub fn all() -> [Self; 3] {
+        [Self::Fit, Self::Stretch, Self::Original]
+    }
+
+    /// Get a scaling from its name, as shown by `Display`
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::all()
+            .iter()
+            .find(|scale| scale.to_string().eq_ignore_ascii_case(name))
+            .cloned()
+    }
+}
+
+impl fmt::Display for BitmapScale {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Fit => write!(f, "Fit"),
+            Self::Stretch => write!(f, "Stretch"),
+            Self::Original => write!(f, "Original"),
+        }
+    }
+}
+
+impl Default for BitmapImport {
+    fn default() -> Self {
+        Self {
+            threshold: DEFAULT_THRESHOLD,
+            dither: false,
+            invert: false,
+            scale: BitmapScale::Fit,
+        }
+    }
+}
+
+/// Get the relative luminance of a color, from 0 to 1
+fn luminance(Rgb([r, g, b]): Rgb<u8>) -> f32 {
+    (0.2126 * f32::from(r) + 0.7152 * f32::from(g) + 0.0722 * f32::from(b)) / 255.0
+}
+
+/// Get the hue of a color, from 0 to 1
+fn hue(Rgb([r, g, b]): Rgb<u8>) -> f32 {
+    let (r, g, b) = (f32::from(r), f32::from(g), f32::from(b));
+    let max = r.max(g).max(b);
+    let delta = max - r.min(g).min(b);
+    if delta == 0.0 {
+        return 0.0;
+    }
+
+    let sector = if max == r {
+        ((g - b) / delta).rem_euclid(6.0)
+    } else if max == g {
+        (b - r) / delta + 2.0
+    } else {
+        (r - g) / delta + 4.0
+    };
+    sector / 6.0
+}
+
+impl BitmapImport {
+    /// Load an image file, with transparent pixels over a white background
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<RgbImage, String> {
+        let image = image::open(path).map_err(|e| e.to_string())?.into_rgba();
+        let (width, height) = image.dimensions();
+
+        Ok(RgbImage::from_fn(width, height, |x, y| {
+            let pixel = image.get_pixel(x, y).0;
+            let alpha = u32::from(pixel[3]);
+            let blend = |c: u8| ((u32::from(c) * alpha + 255 * (255 - alpha)) / 255) as u8;
+            Rgb([blend(pixel[0]), blend(pixel[1]), blend(pixel[2])])
+        }))
+    }
+
+    /// Scale an image for a grid, and get its position on the grid
+    fn fit(&self, image: &RgbImage, (width, height): (usize, usize)) -> (RgbImage, (usize, usize)) {
+        let (image_width, image_height) = (image.width() as usize, image.height() as usize);
+
+        let (target_width, target_height) = match self.scale {
+            BitmapScale::Fit => {
+                let ratio =
+                    (width as f32 / image_width as f32).min(height as f32 / image_height as f32);
+                (
+                    ((image_width as f32 * ratio).round() as usize).clamp(1, width),
+                    ((image_height as f32 * ratio).round() as usize).clamp(1, height),
+                )
+            }
+            BitmapScale::Stretch => (width, height),
+            BitmapScale::Original => {
+                let (crop_width, crop_height) = (image_width.min(width), image_height.min(height));
+                let cropped = imageops::crop_imm(
+                    image,
+                    ((image_width - crop_width) / 2) as u32,
+                    ((image_height - crop_height) / 2) as u32,
+                    crop_width as u32,
+                    crop_height as u32,
+                )
+                .to_image();
+                let offset = ((width - crop_width) / 2, (height - crop_height) / 2);
+                return (cropped, offset);
+            }
+        };
+
+        let scaled = imageops::resize(
+            image,
+            target_width as u32,
+            target_height as u32,
+            FilterType::Triangle,
+        );
+        (
+            scaled,
+            ((width - target_width) / 2, (height - target_height) / 2),
+        )
+    }
+
+    /// Threshold an image, with optional dithering
+    fn threshold(&self, image: &RgbImage) -> Vec<bool> {
+        let (width, height) = (image.width() as usize, image.height() as usize);
+        let mut levels = image
+            .pixels()
+            .map(|pixel| {
+                let level = luminance(*pixel);
+                if self.invert {
+                    level
+                } else {
+                    1.0 - level
+                }
+            })
+            .collect::<Vec<_>>();
+        // Alive when the level is over the threshold, so dark pixels by default
+        let threshold = if self.invert {
+            self.threshold
+        } else {
+            1.0 - self.threshold
+        };
+
+        let mut cells = vec![false; width * height];
+        for y in 0..height {
+            for x in 0..width {
+                let idx = x + y * width;
+                let alive = levels[idx] > threshold;
+                cells[idx] = alive;
+
+                if self.dither {
+                    let error = levels[idx] - if alive { 1.0 } else { 0.0 };
+                    let mut spread = |dx: isize, dy: usize, weight: f32| {
+                        let nx = x as isize + dx;
+                        if nx >= 0 && (nx as usize) < width && y + dy < height {
+                            levels[nx as usize + (y + dy) * width] += error * weight;
+                        }
+                    };
+                    spread(1, 0, 7.0 / 16.0);
+                    spread(-1, 1, 3.0 / 16.0);
+                    spread(0, 1, 5.0 / 16.0);
+                    spread(1, 1, 1.0 / 16.0);
+                }
+            }
+        }
+
+        cells
+    }
+}
+
+impl GameState {
+    /// Replace the grid with an image
+    pub fn import_bitmap(&mut self, image: &RgbImage, settings: &BitmapImport) {
+        let (scaled, (x0, y0)) = settings.fit(image, self.grid_size());
+        let cells = settings.threshold(&scaled);
+        let species_count = self.rule.species_count();
+
+        self.clear();
+        for (x, y, pixel) in scaled.enumerate_pixels() {
+            let (x, y) = (x as usize, y as usize);
+            if !cells[x + y * scaled.width() as usize] {
+                continue;
+            }
+
+            let species = ((hue(*pixel) * f32::from(species_count)) as u8).min(species_count - 1);
+            self.set_cell_at_pos((x0 + x, y0 + y), true, species);
+        }
+        self.stats = GameStats::from_state(self);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::species::QUADLIFE;
+    use super::super::Rule;
+    use super::*;
+
+    fn gray(width: u32, height: u32, level: u8) -> RgbImage {
+        RgbImage::from_pixel(width, height, Rgb([level, level, level]))
+    }
+
+    #[test]
+    fn test_threshold() {
+        let mut image = gray(4, 4, 255);
+        image.put_pixel(1, 2, Rgb([0, 0, 0]));
+        image.put_pixel(3, 0, Rgb([100, 100, 100]));
+
+        let mut state = GameState::new((4, 4));
+        let mut settings = BitmapImport {
+            scale: BitmapScale::Original,
+            ..BitmapImport::default()
+        };
+        state.import_bitmap(&image, &settings);
+        assert_eq!(state.stats.population, 2);
+        assert!(state.data[state.pos_to_index((1, 2))]);
+
+        settings.invert = true;
+        state.import_bitmap(&image, &settings);
+        assert_eq!(state.stats.population, 14);
+    }
+
+    #[test]
+    fn test_dithering() {
+        let mut state = GameState::new((32, 32));
+        let settings = BitmapImport {
+            dither: true,
+            ..BitmapImport::default()
+        };
+
+        // A mid gray is half alive with dithering, and empty without
+        state.import_bitmap(&gray(32, 32, 128), &settings);
+        let density = state.stats.density;
+        assert!(density > 0.4 && density < 0.6, "density {}", density);
+
+        let settings = BitmapImport {
+            dither: false,
+            ..settings
+        };
+        state.import_bitmap(&gray(32, 32, 128), &settings);
+        assert_eq!(state.stats.population, 0);
+    }
+
+    #[test]
+    fn test_scaling() {
+        let black = gray(10, 5, 0);
+        let mut state = GameState::new((20, 20));
+
+        state.import_bitmap(&black, &BitmapImport::default());
+        assert_eq!(state.stats.bounding_box, Some((0, 5, 19, 14)));
+
+        let settings = BitmapImport {
+            scale: BitmapScale::Stretch,
+            ..BitmapImport::default()
+        };
+        state.import_bitmap(&black, &settings);
+        assert_eq!(state.stats.population, 400);
+
+        let settings = BitmapImport {
+            scale: BitmapScale::Original,
+            ..BitmapImport::default()
+        };
+        state.import_bitmap(&black, &settings);
+        assert_eq!(state.stats.bounding_box, Some((5, 7, 14, 11)));
+    }
+
+    #[test]
+    fn test_species_from_hue() {
+        let mut image = gray(2, 1, 0);
+        image.put_pixel(0, 0, Rgb([160, 0, 0]));
+        image.put_pixel(1, 0, Rgb([0, 0, 160]));
+
+        let mut state = GameState::new((2, 1));
+        state.set_rule(Rule::Species(QUADLIFE));
+        let settings = BitmapImport {
+            scale: BitmapScale::Original,
+            ..BitmapImport::default()
+        };
+        state.import_bitmap(&image, &settings);
+
+        assert_eq!(state.stats.population, 2);
+        assert_ne!(state.species[0], state.species[1]);
+    }
+}
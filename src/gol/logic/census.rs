@@ -0,0 +1,304 @@
+//! Object census
+//!
+//! Split the grid in objects, and count them by apgcode.
+//!
+//! Objects are not always connected, like the beacon or the pulsar in some
+//! phases. Connected pieces closer than 3 cells are grouped, then pieces
+//! which evolve independently from the rest of their group are split off
+//! again, so nearby still lifes are still counted separately.
+
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use super::apgcode::{apgcode, common_name, MAX_PERIOD};
+use super::{GameState, Pattern};
+
+/// Label used for objects which could not be identified
+pub const UNIDENTIFIED: &str = "unidentified";
+
+/// Object counts at one generation
+#[derive(Clone, Debug, Default)]
+pub struct Census {
+    pub generation: usize,
+    /// Object counts, by apgcode
+    pub objects: BTreeMap<String, usize>,
+}
+
+impl Census {
+    /// Take a census of a game state
+    pub fn from_state(state: &GameState) -> Self {
+        let mut cache: HashMap<Pattern, String> = HashMap::new();
+        let mut objects = BTreeMap::new();
+
+        for object in split_objects(state) {
+            let shape = object.normalize();
+            let code = cache
+                .entry(shape)
+                .or_insert_with_key(|shape| {
+                    apgcode(shape).unwrap_or_else(|| UNIDENTIFIED.to_string())
+                })
+                .clone();
+            *objects.entry(code).or_insert(0) += 1;
+        }
+
+        Self {
+            generation: state.current_tick,
+            objects,
+        }
+    }
+
+    /// Get the total object count
+    pub fn total(&self) -> usize {
+        self.objects.values().sum()
+    }
+
+    /// Get objects sorted by decreasing count
+    pub fn sorted(&self) -> Vec<(&str, usize)> {
+        let mut objects = self
+            .objects
+            .iter()
+            .map(|(code, count)| (code.as_str(), *count))
+            .collect::<Vec<_>>();
+        objects.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(b.0)));
+        objects
+    }
+
+    /// Get the census as CSV, with `apgcode,name,count` columns
+    pub fn to_csv(&self) -> String {
+        let mut output = String::from("apgcode,name,count\n");
+        for (code, count) in self.sorted() {
+            output.push_str(&format!(
+                "{},{},{}\n",
+                code,
+                common_name(code).unwrap_or(""),
+                count
+            ));
+        }
+
+        output
+    }
+
+    /// Write the census to a CSV file
+    pub fn write_csv<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        fs::write(path, self.to_csv())
+    }
+}
+
+/// Maximum distance between the cells of two pieces which can interact
+const INTERACTION_DISTANCE: i32 = 2;
+
+/// Split the live cells of a game state into objects
+fn split_objects(state: &GameState) -> Vec<Pattern> {
+    let pieces = Pattern::components(state);
+    let size = (state.width as i32, state.height as i32);
+
+    let mut objects = vec![];
+    let mut grouped = vec![false; pieces.len()];
+    for start in 0..pieces.len() {
+        if grouped[start] {
+            continue;
+        }
+
+        // Gather the nearby pieces, moved next to each other across edges
+        grouped[start] = true;
+        let mut group = vec![pieces[start].clone()];
+        let mut next = 0;
+        while next < group.len() {
+            for (index, piece) in pieces.iter().enumerate() {
+                if grouped[index] {
+                    continue;
+                }
+                if let Some(shift) = nearby_shift(&group[next], piece, size) {
+                    grouped[index] = true;
+                    group.push(piece.translate(shift));
+                }
+            }
+            next += 1;
+        }
+
+        split_group(group, &mut objects);
+    }
+
+    objects
+}
+
+/// Get the translation moving a piece within interaction distance of
+/// another one on the torus, if they are close enough
+fn nearby_shift(a: &Pattern, b: &Pattern, (width, height): (i32, i32)) -> Option<(i32, i32)> {
+    let wrap = |d: i32, size: i32| {
+        let d = d.rem_euclid(size);
+        if d > size / 2 {
+            d - size
+        } else {
+            d
+        }
+    };
+
+    a.cells.iter().find_map(|(ax, ay)| {
+        b.cells.iter().find_map(|(bx, by)| {
+            let (dx, dy) = (wrap(bx - ax, width), wrap(by - ay, height));
+            (dx.abs() <= INTERACTION_DISTANCE && dy.abs() <= INTERACTION_DISTANCE)
+                .then_some((ax + dx - bx, ay + dy - by))
+        })
+    })
+}
+
+/// Split a group of nearby pieces into objects, keeping the pieces which
+/// interact together
+fn split_group(mut group: Vec<Pattern>, objects: &mut Vec<Pattern>) {
+    while group.len() > 1 {
+        let rest_of = |index: usize, group: &[Pattern]| {
+            merge(
+                group
+                    .iter()
+                    .enumerate()
+                    .filter(|(other, _)| *other != index)
+                    .map(|(_, piece)| piece),
+            )
+        };
+        let independent = (0..group.len())
+            .find(|index| evolve_independently(&group[*index], &rest_of(*index, &group)));
+
+        match independent {
+            Some(index) => objects.push(group.remove(index)),
+            None => break,
+        }
+    }
+
+    objects.push(merge(group.iter()));
+}
+
+fn merge<'a>(pieces: impl Iterator<Item = &'a Pattern>) -> Pattern {
+    Pattern::new(
+        pieces
+            .flat_map(|piece| piece.cells.iter().copied())
+            .collect(),
+    )
+}
+
+/// Check if two patterns evolve as if they were alone, over the period of
+/// the whole. Non periodic wholes are kept apart, as they cannot be
+/// identified anyway.
+fn evolve_independently(a: &Pattern, b: &Pattern) -> bool {
+    let mut whole = merge([a, b].iter().copied());
+    let period = match whole.find_period(MAX_PERIOD) {
+        Some((period, _)) => period,
+        None => return true,
+    };
+
+    let (mut a, mut b) = (a.clone(), b.clone());
+    for _ in 0..period {
+        whole = whole.step();
+        a = a.step();
+        b = b.step();
+        if whole != merge([&a, &b].iter().copied()) {
+            return false;
+        }
+    }
+
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_census() {
+        let mut state = GameState::new((32, 32));
+        // Two blocks, one of them crossing the edge
+        for pos in &[
+            (2, 2),
+            (3, 2),
+            (2, 3),
+            (3, 3),
+            (31, 10),
+            (0, 10),
+            (31, 11),
+            (0, 11),
+        ] {
+            state.set_value_at_pos(*pos, true);
+        }
+        // Vertical blinker
+        for pos in &[(10, 10), (10, 11), (10, 12)] {
+            state.set_value_at_pos(*pos, true);
+        }
+        // Glider
+        for pos in &[(21, 20), (22, 21), (20, 22), (21, 22), (22, 22)] {
+            state.set_value_at_pos(*pos, true);
+        }
+
+        let census = Census::from_state(&state);
+        assert_eq!(census.total(), 4);
+        assert_eq!(census.objects["xs4_33"], 2);
+        assert_eq!(census.objects["xp2_7"], 1);
+        assert_eq!(census.objects["xq4_153"], 1);
+        assert_eq!(census.sorted()[0], ("xs4_33", 2));
+        assert!(census
+            .to_csv()
+            .starts_with("apgcode,name,count\nxs4_33,block,2\n"));
+    }
+
+    fn paste(state: &mut GameState, cells: &[(usize, usize)], (x, y): (usize, usize)) {
+        for (cx, cy) in cells {
+            state.set_value_at_pos(((x + cx) % state.width, (y + cy) % state.height), true);
+        }
+    }
+
+    #[test]
+    fn test_disconnected_objects() {
+        let beacon = [(0, 0), (1, 0), (0, 1), (3, 2), (2, 3), (3, 3)];
+        let block = [(0, 0), (1, 0), (0, 1), (1, 1)];
+
+        let mut state = GameState::new((40, 40));
+        // Beacon in its 6 cells phase, and a block
+        paste(&mut state, &beacon, (2, 2));
+        paste(&mut state, &block, (2, 12));
+        // Beacon in its 8 cells phase, crossing the edge
+        paste(&mut state, &beacon, (38, 20));
+        state.set_value_at_pos((39, 21), true);
+        state.set_value_at_pos((0, 22), true);
+        // Two blocks, 2 cells apart but not interacting
+        paste(&mut state, &block, (20, 2));
+        paste(&mut state, &block, (23, 2));
+
+        let census = Census::from_state(&state);
+        assert_eq!(census.objects["xp2_318c"], 2);
+        assert_eq!(census.objects["xs4_33"], 3);
+        assert_eq!(census.total(), 5);
+    }
+
+    #[test]
+    fn test_pulsar() {
+        // One quadrant of the pulsar, as distances from its center
+        let mut quadrant = vec![];
+        for side in 2..5 {
+            for bar in &[1, 6] {
+                quadrant.push((side, *bar));
+                quadrant.push((*bar, side));
+            }
+        }
+        let mut state = GameState::new((32, 32));
+        for (x, y) in &quadrant {
+            for (mx, my) in &[(1, 1), (-1, 1), (1, -1), (-1, -1)] {
+                let pos = (16 + mx * x, 16 + my * y);
+                state.set_value_at_pos((pos.0 as usize, pos.1 as usize), true);
+            }
+        }
+
+        // Every phase is a single pulsar, even when split in pieces
+        for _ in 0..3 {
+            let census = Census::from_state(&state);
+            assert_eq!(census.total(), 1);
+            assert_eq!(
+                common_name(census.sorted()[0].0),
+                Some("pulsar"),
+                "{:?}",
+                census.objects
+            );
+            state.tick();
+        }
+    }
+}
@@ -0,0 +1,268 @@
+//! Undo/redo history
+
+use std::collections::VecDeque;
+
+use super::{GameState, Snapshot};
+
+/// Default history memory budget, in bytes
+pub const DEFAULT_MEMORY_BUDGET: usize = 128 * 1024 * 1024;
+
+/// History entry: state before an action
+struct Entry {
+    label: &'static str,
+    snapshot: Snapshot,
+}
+
+/// Undo/redo history, bounded by a memory budget
+pub struct History {
+    undo: VecDeque<Entry>,
+    redo: Vec<Entry>,
+    memory_budget: usize,
+    /// Memory used by undo and redo entries, in bytes
+    memory_used: usize,
+    /// Edit counter of the state when the current run was recorded
+    run_edits: Option<usize>,
+}
+
+impl History {
+    pub fn new(memory_budget: usize) -> Self {
+        Self {
+            undo: VecDeque::new(),
+            redo: vec![],
+            memory_budget,
+            memory_used: 0,
+            run_edits: None,
+        }
+    }
+
+    /// Record the state before an action
+    pub fn record(&mut self, label: &'static str, state: &GameState) {
+        let snapshot = state.snapshot();
+        self.memory_used += snapshot.memory_size();
+        self.undo.push_back(Entry { label, snapshot });
+        self.run_edits = None;
+
+        for entry in self.redo.drain(..) {
+            self.memory_used -= entry.snapshot.memory_size();
+        }
+
+        self.forget_oldest();
+    }
+
+    /// Record the state before running generations, once for all the
+    /// generations run between two edits
+    pub fn record_run(&mut self, state: &GameState) {
+        if self.run_edits != Some(state.edits) {
+            self.record("Run", state);
+            self.run_edits = Some(state.edits);
+        }
+    }
+
+    /// Forget the oldest entries, until the memory budget is met
+    fn forget_oldest(&mut self) {
+        while self.memory_used > self.memory_budget && self.undo.len() > 1 {
+            if let Some(entry) = self.undo.pop_front() {
+                self.memory_used -= entry.snapshot.memory_size();
+            }
+        }
+    }
+
+    /// Replace a state by an entry, getting the entry for the state
+    fn swap(&mut self, entry: Entry, state: &mut GameState) -> Entry {
+        let snapshot = state.snapshot();
+        self.memory_used -= entry.snapshot.memory_size();
+        self.memory_used += snapshot.memory_size();
+        state.restore(&entry.snapshot);
+
+        Entry {
+            label: entry.label,
+            snapshot,
+        }
+    }
+
+    /// Undo the last action
+    pub fn undo(&mut self, state: &mut GameState) -> bool {
+        match self.undo.pop_back() {
+            Some(entry) => {
+                let entry = self.swap(entry, state);
+                self.redo.push(entry);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Redo the last undone action
+    pub fn redo(&mut self, state: &mut GameState) -> bool {
+        match self.redo.pop() {
+            Some(entry) => {
+                let entry = self.swap(entry, state);
+                self.undo.push_back(entry);
+                self.forget_oldest();
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Get the label of the next action to undo
+    pub fn undo_label(&self) -> Option<&'static str> {
+        self.undo.back().map(|e| e.label)
+    }
+
+    /// Get the label of the next action to redo
+    pub fn redo_label(&self) -> Option<&'static str> {
+        self.redo.last().map(|e| e.label)
+    }
+
+    pub fn clear(&mut self) {
+        self.undo.clear();
+        self.redo.clear();
+        self.memory_used = 0;
+        self.run_edits = None;
+    }
+
+    pub fn memory_used(&self) -> usize {
+        self.memory_used
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::{Anchor, Rule, VolumeRule};
+    use super::*;
+
+    #[test]
+    fn test_undo_redo() {
+        let mut state = GameState::new((4, 4));
+        let mut history = History::new(DEFAULT_MEMORY_BUDGET);
+
+        history.record("Brush", &state);
+        state.set_value_at_pos((1, 1), true);
+        history.record("Brush", &state);
+        state.set_value_at_pos((2, 2), true);
+        let drawn = state.data.clone();
+
+        assert!(history.undo(&mut state));
+        assert!(!state.data[state.pos_to_index((2, 2))]);
+        assert!(state.data[state.pos_to_index((1, 1))]);
+        assert!(history.undo(&mut state));
+        assert!(!state.data.iter().any(|x| *x));
+        assert!(!history.undo(&mut state));
+
+        assert!(history.redo(&mut state));
+        assert!(history.redo(&mut state));
+        assert_eq!(state.data, drawn);
+        assert!(!history.redo(&mut state));
+    }
+
+    #[test]
+    fn test_record_clears_redo() {
+        let mut state = GameState::new((4, 4));
+        let mut history = History::new(DEFAULT_MEMORY_BUDGET);
+
+        history.record("Clear", &state);
+        state.randomize();
+        history.undo(&mut state);
+        assert_eq!(history.redo_label(), Some("Clear"));
+
+        history.record("Randomize", &state);
+        assert_eq!(history.redo_label(), None);
+        assert_eq!(history.undo_label(), Some("Randomize"));
+    }
+
+    #[test]
+    fn test_memory_budget() {
+        let mut state = GameState::new((10, 10));
+        let snapshot_size = state.snapshot().memory_size();
+        let mut history = History::new(snapshot_size * 3);
+
+        for _ in 0..10 {
+            history.record("Step", &state);
+            state.tick();
+        }
+
+        assert!(history.memory_used() <= snapshot_size * 3);
+        let mut count = 0;
+        while history.undo(&mut state) {
+            count += 1;
+        }
+        assert_eq!(count, 3);
+        assert_eq!(state.current_tick, 7);
+    }
+
+    #[test]
+    fn test_memory_with_snapshot_sizes() {
+        let mut state = GameState::new((4, 4));
+        let mut history = History::new(DEFAULT_MEMORY_BUDGET);
+        let small = state.snapshot().memory_size();
+
+        history.record("Resize grid", &state);
+        state.resize((64, 64), Anchor::Center);
+        let large = state.snapshot().memory_size();
+
+        assert!(history.undo(&mut state));
+        assert_eq!(history.memory_used(), large);
+        history.record("Brush", &state);
+        assert_eq!(history.memory_used(), small);
+
+        assert!(history.undo(&mut state));
+        assert!(history.redo(&mut state));
+        assert_eq!(history.memory_used(), small);
+        assert_eq!(history.undo_label(), Some("Brush"));
+    }
+
+    #[test]
+    fn test_record_run() {
+        let mut state = GameState::new((4, 4));
+        let mut history = History::new(DEFAULT_MEMORY_BUDGET);
+
+        history.record("Brush", &state);
+        state.set_value_at_pos((1, 1), true);
+        for _ in 0..5 {
+            history.record_run(&state);
+            state.tick();
+        }
+        assert_eq!(history.undo_label(), Some("Run"));
+
+        // The whole run is undone at once
+        assert!(history.undo(&mut state));
+        assert_eq!(state.current_tick, 0);
+        assert_eq!(history.undo_label(), Some("Brush"));
+
+        // Runs after an edit get their own entry
+        history.record_run(&state);
+        state.tick();
+        state.set_value_at_pos((2, 2), true);
+        history.record_run(&state);
+        assert!(history.undo(&mut state));
+        assert!(history.undo(&mut state));
+        assert_eq!(history.undo_label(), Some("Brush"));
+    }
+
+    #[test]
+    fn test_undo_rule_change() {
+        let mut state = GameState::new((4, 4));
+        let mut history = History::new(DEFAULT_MEMORY_BUDGET);
+        let volume_rule = Rule::Volume(VolumeRule::parse("4/4/5/M").unwrap());
+
+        history.record("Change rule", &state);
+        state.set_rule(volume_rule);
+        assert!(state.snapshot().volume.is_some());
+        history.record("Change rule", &state);
+        state.set_rule(Rule::Life);
+
+        // The inactive 3D grid is not copied
+        let snapshot = state.snapshot();
+        assert!(snapshot.volume.is_none());
+        assert_eq!(snapshot.memory_size(), 4 * 16);
+
+        assert!(history.undo(&mut state));
+        assert_eq!(state.rule, volume_rule);
+        assert!(state.active_volume().is_some());
+        assert!(history.undo(&mut state));
+        assert_eq!(state.rule, Rule::Life);
+        assert!(history.redo(&mut state));
+        assert_eq!(state.rule, volume_rule);
+    }
+}
@@ -0,0 +1,93 @@
+//! Margolus neighborhood block rules
+//!
+//! The grid is partitioned in 2x2 blocks, whose offset alternates each
+//! generation. Each block is then replaced using a 16-entry table, indexed
+//! by the block bits:
+//!
+//! ```text
+//! 0 1
+//! 2 3
+//! ```
+
+/// Margolus block rule
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct MargolusRule {
+    pub name: &'static str,
+    pub table: [u8; 16],
+}
+
+/// Billiard Ball Machine: single cells move diagonally, opposite pairs turn by 90°
+pub const BILLIARD_BALL_MACHINE: MargolusRule = MargolusRule {
+    name: "Billiard Ball Machine",
+    table: [0, 8, 4, 3, 2, 5, 9, 7, 1, 6, 10, 11, 12, 13, 14, 15],
+};
+
+/// Critters: invert blocks without exactly two cells, rotate blocks of three cells
+pub const CRITTERS: MargolusRule = MargolusRule {
+    name: "Critters",
+    table: [15, 14, 13, 3, 11, 5, 6, 1, 7, 9, 10, 2, 12, 4, 8, 0],
+};
+
+/// Tron: invert uniform blocks
+pub const TRON: MargolusRule = MargolusRule {
+    name: "Tron",
+    table: [15, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 0],
+};
+
+impl MargolusRule {
+    /// Get preset rules
+    pub fn presets() -> Vec<Self> {
+        vec![BILLIARD_BALL_MACHINE, CRITTERS, TRON]
+    }
+
+    /// Check if the table is a permutation, so the rule can be reversed
+    pub fn is_permutation(&self) -> bool {
+        let mut seen = [false; 16];
+        for value in self.table.iter() {
+            let value = (*value & 15) as usize;
+            if seen[value] {
+                return false;
+            }
+            seen[value] = true;
+        }
+
+        true
+    }
+
+    /// Get the inverse table
+    pub fn inverse_table(&self) -> [u8; 16] {
+        let mut table = [0; 16];
+        for (block, value) in self.table.iter().enumerate() {
+            table[(*value & 15) as usize] = block as u8;
+        }
+
+        table
+    }
+
+    /// Get block offset for a generation
+    pub fn offset_for_tick(tick: usize) -> usize {
+        tick % 2
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_presets_are_permutations() {
+        for rule in MargolusRule::presets() {
+            assert!(rule.is_permutation(), "{} is not reversible", rule.name);
+        }
+    }
+
+    #[test]
+    fn test_inverse_table() {
+        for rule in MargolusRule::presets() {
+            let inverse = rule.inverse_table();
+            for block in 0..16 {
+                assert_eq!(inverse[rule.table[block] as usize] as usize, block);
+            }
+        }
+    }
+}
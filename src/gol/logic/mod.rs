@@ -0,0 +1,678 @@
+//! Game of Life logic
+#![allow(dead_code)]
+
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+
+mod apgcode;
+mod bitmap;
+mod census;
+mod history;
+mod margolus;
+mod motion;
+mod pattern;
+mod pattern_file;
+mod period;
+mod population;
+mod resize;
+mod rule;
+mod scheduler;
+mod search;
+mod second_order;
+mod snapshot;
+mod soup;
+mod species;
+mod stats;
+mod tiling;
+mod timeline;
+mod volume;
+mod workspace;
+
+pub use self::apgcode::common_name;
+pub use self::bitmap::{BitmapImport, BitmapScale};
+pub use self::census::Census;
+pub use self::history::{History, DEFAULT_MEMORY_BUDGET};
+pub use self::margolus::MargolusRule;
+pub use self::motion::{Motion, TrackedObject, ANALYSIS_BUDGET};
+pub use self::pattern::Pattern;
+pub use self::pattern_file::PatternFile;
+pub use self::period::{CycleDetector, Periodicity};
+pub use self::population::PopulationHistory;
+pub use self::resize::Anchor;
+pub use self::rule::Rule;
+pub use self::scheduler::{Scheduler, MAX_SPEED, MIN_SPEED};
+pub use self::search::{Search, SearchConfig};
+pub use self::snapshot::Snapshot;
+pub use self::soup::{SoupSettings, Symmetry};
+pub use self::stats::{GameStats, DEFAULT_ENTROPY_BLOCK_SIZES};
+pub use self::tiling::Tiling;
+pub use self::timeline::{Timeline, REPLAY_BUDGET};
+pub use self::volume::{Volume, VolumeRule, VolumeView};
+pub use self::workspace::Workspace;
+
+use self::species::SpeciesRule;
+use self::stats::StatsBuilder;
+
+const MAX_LIFE: u8 = 200;
+
+#[derive(Clone)]
+pub struct GameState {
+    pub width: usize,
+    pub height: usize,
+    pub data: Vec<bool>,
+    pub previous: Vec<bool>,
+    pub species: Vec<u8>,
+    pub life: Vec<u8>,
+    pub current_tick: usize,
+    /// Edit counter, increased when cells are changed outside of the simulation
+    pub edits: usize,
+    pub scheduler: Scheduler,
+    pub running: bool,
+    pub rule: Rule,
+    pub volume: Option<Volume>,
+    pub cycle_detector: CycleDetector,
+    /// Pause when the grid becomes periodic
+    pub auto_pause: bool,
+    /// Watch for periodicity even without auto-pause
+    pub detect_cycles: bool,
+    pub stats: GameStats,
+    /// Block sizes used for spatial entropy stats
+    pub entropy_block_sizes: Vec<usize>,
+    pub soup: SoupSettings,
+    /// Generator of soup seeds
+    rng: StdRng,
+}
+
+impl GameState {
+    pub fn new((width, height): (usize, usize)) -> Self {
+        Self {
+            width,
+            height,
+            data: vec![false; width * height],
+            previous: vec![false; width * height],
+            species: vec![0; width * height],
+            life: vec![0; width * height],
+            current_tick: 0,
+            edits: 0,
+            scheduler: Scheduler::new(),
+            running: true,
+            rule: Rule::default(),
+            volume: None,
+            cycle_detector: CycleDetector::new(),
+            auto_pause: false,
+            detect_cycles: false,
+            stats: GameStats::new(),
+            entropy_block_sizes: DEFAULT_ENTROPY_BLOCK_SIZES.to_vec(),
+            soup: SoupSettings::default(),
+            rng: StdRng::from_entropy(),
+        }
+    }
+
+    pub fn size(&self) -> usize {
+        self.width * self.height
+    }
+
+    /// Change the current rule, creating the 3D grid if needed
+    pub fn set_rule(&mut self, rule: Rule) {
+        self.rule = rule;
+        self.fit_size_to_rule();
+
+        // Keep species known by the rule
+        let max_species = rule.species_count() - 1;
+        for species in self.species.iter_mut() {
+            *species = (*species).min(max_species);
+        }
+
+        if let Rule::Volume(_) = rule {
+            if self.volume.is_none() {
+                let mut volume = Volume::new(volume::DEFAULT_VOLUME_SIZE);
+                volume.randomize(&mut self.rng);
+                self.volume = Some(volume);
+            }
+        }
+    }
+
+    /// Get the 3D grid, if the current rule is a 3D rule
+    pub fn active_volume(&self) -> Option<&Volume> {
+        match self.rule {
+            Rule::Volume(_) => self.volume.as_ref(),
+            _ => None,
+        }
+    }
+
+    fn active_volume_mut(&mut self) -> Option<&mut Volume> {
+        match self.rule {
+            Rule::Volume(_) => self.volume.as_mut(),
+            _ => None,
+        }
+    }
+
+    /// Get the size of the displayed grid
+    pub fn grid_size(&self) -> (usize, usize) {
+        match self.active_volume() {
+            Some(volume) => (volume.width, volume.height),
+            None => (self.width, self.height),
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.edits += 1;
+        if let Some(volume) = self.active_volume_mut() {
+            return volume.clear();
+        }
+
+        self.data = vec![false; self.width * self.height];
+        self.previous = vec![false; self.width * self.height];
+        self.species = vec![0; self.width * self.height];
+        self.life = vec![0; self.width * self.height];
+    }
+
+    pub fn set_value_at_pos(&mut self, pos: (usize, usize), value: bool) {
+        self.set_cell_at_pos(pos, value, 0);
+    }
+
+    pub fn set_cell_at_pos(&mut self, pos: (usize, usize), value: bool, species: u8) {
+        self.edits += 1;
+        if let Some(volume) = self.active_volume_mut() {
+            let slice = volume.slice;
+            return volume.set_value_at_pos((pos.0, pos.1, slice), value);
+        }
+
+        let pos = self.pos_to_index(pos);
+        self.data[pos] = value;
+        self.species[pos] = species;
+        self.life[pos] = 0;
+    }
+
+    pub fn set_value_at_pos_with_radius(
+        &mut self,
+        pos: (usize, usize),
+        radius: usize,
+        value: bool,
+        species: u8,
+    ) {
+        let radius = radius as isize / 2;
+        if radius == 0 {
+            return self.set_cell_at_pos(pos, value, species);
+        }
+
+        let (width, height) = self.grid_size();
+        for ry in -radius..radius {
+            for rx in -radius..radius {
+                if rx * rx + ry * ry <= radius * radius {
+                    let wrapped = (
+                        (pos.0 as isize + rx).rem_euclid(width as isize) as usize,
+                        (pos.1 as isize + ry).rem_euclid(height as isize) as usize,
+                    );
+                    self.set_cell_at_pos(wrapped, value, species);
+                }
+            }
+        }
+    }
+
+    pub fn index_to_pos(&self, idx: usize) -> (usize, usize) {
+        (idx % self.width, idx / self.width)
+    }
+
+    pub fn pos_to_index(&self, (x, y): (usize, usize)) -> usize {
+        x + y * self.width
+    }
+
+    fn wrap_positions(&self, (x, y): (isize, isize)) -> (usize, usize) {
+        (
+            x.rem_euclid(self.width as isize) as usize,
+            y.rem_euclid(self.height as isize) as usize,
+        )
+    }
+
+    fn alive_neighbors_count_for_index(&self, idx: usize) -> usize {
+        self.alive_neighbors_count_in(&self.data, idx)
+    }
+
+    fn alive_neighbors_count_in(&self, data: &[bool], idx: usize) -> usize {
+        self.neighbor_indices(idx).filter(|idx| data[*idx]).count()
+    }
+
+    fn neighbor_indices(&self, idx: usize) -> impl Iterator<Item = usize> + '_ {
+        let (x, y) = self.index_to_pos(idx);
+        self.rule
+            .tiling()
+            .neighbor_offsets((x, y))
+            .iter()
+            .map(move |(ox, oy)| self.wrap_positions((*ox + x as isize, oy + y as isize)))
+            .map(move |pos| self.pos_to_index(pos))
+    }
+
+    /// Check if the grid is watched for periodicity
+    pub fn watches_cycles(&self) -> bool {
+        self.auto_pause || self.detect_cycles
+    }
+
+    pub fn tick(&mut self) {
+        if !self.watches_cycles() {
+            self.cycle_detector.reset();
+            self.stats.periodicity = None;
+            self.run_generation();
+            return;
+        }
+
+        // Start watching for periodicity from the current generation
+        if !self.cycle_detector.is_tracking(self) {
+            self.cycle_detector.reset();
+            self.observe_cycle();
+        }
+
+        self.run_generation();
+
+        let detected = self.observe_cycle();
+        self.stats.periodicity = self.cycle_detector.result;
+        if detected && self.auto_pause {
+            self.running = false;
+            self.scheduler.cancel();
+        }
+    }
+
+    fn observe_cycle(&mut self) -> bool {
+        let mut detector = std::mem::replace(&mut self.cycle_detector, CycleDetector::new());
+        let detected = detector.observe(self);
+        self.cycle_detector = detector;
+        detected
+    }
+
+    fn run_generation(&mut self) {
+        if let Rule::Volume(rule) = self.rule {
+            if let Some(volume) = self.volume.as_mut() {
+                volume.tick(&rule);
+            }
+            self.stats = GameStats::from_state(self);
+            self.current_tick += 1;
+            return;
+        }
+
+        let new_data = match self.rule {
+            Rule::Life => self.life_like_generation(&self.data, &[3], &[2, 3]),
+            Rule::Margolus(rule) => {
+                let offset = MargolusRule::offset_for_tick(self.current_tick);
+                self.margolus_generation(&rule.table, offset)
+            }
+            Rule::SecondOrder(rule) => self
+                .life_like_generation(&self.data, rule.birth, rule.survival)
+                .iter()
+                .zip(self.previous.iter())
+                .map(|(state, previous)| state ^ previous)
+                .collect(),
+            Rule::Species(rule) => {
+                let new_data = self.life_like_generation(&self.data, &[3], &[2, 3]);
+                self.species = self.species_generation(&new_data, &rule);
+                new_data
+            }
+            Rule::Tiled(rule) => self.life_like_generation(&self.data, rule.birth, rule.survival),
+            Rule::Volume(_) => unreachable!(),
+        };
+
+        self.commit_generation(new_data);
+        self.current_tick += 1;
+    }
+
+    /// Step one generation backwards, for reversible rules only
+    pub fn tick_back(&mut self) {
+        if !self.can_tick_back() {
+            return;
+        }
+
+        match self.rule {
+            Rule::Life | Rule::Species(_) | Rule::Tiled(_) | Rule::Volume(_) => unreachable!(),
+            Rule::Margolus(rule) => {
+                let offset = MargolusRule::offset_for_tick(self.current_tick - 1);
+                let new_data = self.margolus_generation(&rule.inverse_table(), offset);
+                self.commit_generation(new_data);
+            }
+            Rule::SecondOrder(rule) => {
+                let new_previous = self
+                    .life_like_generation(&self.previous, rule.birth, rule.survival)
+                    .iter()
+                    .zip(self.data.iter())
+                    .map(|(state, next)| state ^ next)
+                    .collect();
+                let new_data = self.previous.clone();
+                self.commit_generation(new_data);
+                self.previous = new_previous;
+            }
+        }
+
+        self.current_tick -= 1;
+    }
+
+    pub fn can_tick_back(&self) -> bool {
+        self.rule.is_reversible() && self.current_tick > 0
+    }
+
+    fn life_like_generation(
+        &self,
+        data: &[bool],
+        birth: &[usize],
+        survival: &[usize],
+    ) -> Vec<bool> {
+        data.iter()
+            .enumerate()
+            .map(|(idx, alive)| {
+                let count = self.alive_neighbors_count_in(data, idx);
+                if *alive {
+                    survival.contains(&count)
+                } else {
+                    birth.contains(&count)
+                }
+            })
+            .collect()
+    }
+
+    fn species_generation(&self, new_data: &[bool], rule: &SpeciesRule) -> Vec<u8> {
+        new_data
+            .iter()
+            .enumerate()
+            .map(|(idx, alive)| {
+                if !alive || self.data[idx] {
+                    return self.species[idx];
+                }
+
+                let parents = self
+                    .neighbor_indices(idx)
+                    .filter(|idx| self.data[*idx])
+                    .map(|idx| self.species[idx])
+                    .collect::<Vec<_>>();
+                rule.birth_species(&parents)
+            })
+            .collect()
+    }
+
+    fn margolus_generation(&self, table: &[u8; 16], offset: usize) -> Vec<bool> {
+        let mut new_data = self.data.clone();
+
+        for by in 0..self.height / 2 {
+            for bx in 0..self.width / 2 {
+                let (x, y) = ((bx * 2 + offset) as isize, (by * 2 + offset) as isize);
+                let block = [(x, y), (x + 1, y), (x, y + 1), (x + 1, y + 1)]
+                    .iter()
+                    .map(|pos| self.pos_to_index(self.wrap_positions(*pos)))
+                    .collect::<Vec<_>>();
+
+                let value = block
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, idx)| self.data[**idx])
+                    .fold(0, |acc, (bit, _)| acc | 1 << bit);
+                let new_value = table[value];
+
+                for (bit, idx) in block.iter().enumerate() {
+                    new_data[*idx] = new_value & (1 << bit) != 0;
+                }
+            }
+        }
+
+        new_data
+    }
+
+    fn commit_generation(&mut self, new_data: Vec<bool>) {
+        let mut stats = StatsBuilder::new(self);
+
+        for (idx, alive) in self.data.iter().enumerate() {
+            let life = self.life[idx];
+            let state = new_data[idx];
+
+            if *alive && state == *alive {
+                // More life
+                let new_life = if life + 1 >= MAX_LIFE {
+                    MAX_LIFE
+                } else {
+                    life + 1
+                };
+                self.life[idx] = new_life;
+            } else {
+                self.life[idx] = 0;
+            }
+
+            stats.add(idx, *alive, state, self.life[idx], self.species[idx]);
+        }
+
+        self.previous = std::mem::replace(&mut self.data, new_data);
+        self.stats = stats.finish(self);
+    }
+
+    /// Get the number of generations to run for an update of `dt` seconds
+    pub fn scheduled_ticks(&mut self, dt: f32) -> usize {
+        self.scheduler
+            .scheduled_ticks(dt, self.running, self.current_tick)
+    }
+
+    /// Run generations and update stats
+    pub fn cycle(&mut self, ticks: usize) {
+        self.cycle_with(ticks, |_| {});
+    }
+
+    /// Run generations, calling `on_tick` after each one
+    pub fn cycle_with(&mut self, ticks: usize, mut on_tick: impl FnMut(&GameState)) {
+        if ticks == 0 {
+            return;
+        }
+
+        for _ in 0..ticks {
+            let was_running = self.running;
+            self.tick();
+            on_tick(self);
+
+            // Auto pause
+            if was_running && !self.running {
+                break;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pos_to_index() {
+        let state = GameState::new((10, 10));
+        assert_eq!(state.pos_to_index((0, 0)), 0);
+        assert_eq!(state.pos_to_index((9, 0)), 9);
+        assert_eq!(state.pos_to_index((0, 9)), 90);
+        assert_eq!(state.pos_to_index((9, 9)), 99);
+    }
+
+    #[test]
+    fn test_index_to_pos() {
+        let state = GameState::new((10, 10));
+        assert_eq!(state.index_to_pos(0), (0, 0));
+        assert_eq!(state.index_to_pos(9), (9, 0));
+        assert_eq!(state.index_to_pos(90), (0, 9));
+        assert_eq!(state.index_to_pos(99), (9, 9));
+    }
+
+    #[test]
+    fn test_alive_neighbors_count_for_index() {
+        let mut state = GameState::new((3, 3));
+
+        // $+-
+        // ++-
+        // --+
+        // => 4
+        {
+            state.clear();
+            state.set_value_at_pos((1, 0), true);
+            state.set_value_at_pos((0, 1), true);
+            state.set_value_at_pos((1, 1), true);
+            state.set_value_at_pos((2, 2), true);
+            assert_eq!(state.alive_neighbors_count_for_index(0), 4);
+        }
+
+        // -+-
+        // +$-
+        // --+
+        // => 3
+        {
+            state.clear();
+            state.set_value_at_pos((1, 0), true);
+            state.set_value_at_pos((0, 1), true);
+            state.set_value_at_pos((2, 2), true);
+            assert_eq!(
+                state.alive_neighbors_count_for_index(state.pos_to_index((1, 1))),
+                3
+            );
+        }
+    }
+
+    #[test]
+    fn test_margolus_tick_back() {
+        for rule in MargolusRule::presets() {
+            let mut state = GameState::new((16, 12));
+            state.rule = Rule::Margolus(rule);
+            state.randomize();
+            let initial = state.data.clone();
+
+            for _ in 0..25 {
+                state.tick();
+            }
+            for _ in 0..25 {
+                state.tick_back();
+            }
+
+            assert_eq!(state.current_tick, 0);
+            assert_eq!(state.data, initial, "{} is not reversed", rule.name);
+        }
+    }
+
+    #[test]
+    fn test_second_order_tick_back() {
+        for rule in second_order::SecondOrderRule::presets() {
+            let mut state = GameState::new((20, 15));
+            state.rule = Rule::SecondOrder(rule);
+            state.randomize();
+            let previous = state.data.clone();
+            state.randomize();
+            state.previous = previous;
+
+            let initial = (state.data.clone(), state.previous.clone());
+
+            for _ in 0..40 {
+                state.tick();
+            }
+            assert_ne!(state.data, initial.0);
+
+            for _ in 0..40 {
+                state.tick_back();
+            }
+
+            assert_eq!(state.current_tick, 0);
+            assert_eq!(state.data, initial.0, "{} is not reversed", rule.name);
+            assert_eq!(state.previous, initial.1, "{} is not reversed", rule.name);
+        }
+    }
+
+    #[test]
+    fn test_tiling_neighbors() {
+        for (rule, neighbors) in &[
+            (Rule::Life, 8),
+            (Rule::Tiled(tiling::HEX_LIFE), 6),
+            (Rule::Tiled(tiling::TRIANGULAR_LIFE), 12),
+        ] {
+            let mut state = GameState::new((8, 6));
+            state.rule = *rule;
+
+            for idx in 0..state.size() {
+                let mut indices = state.neighbor_indices(idx).collect::<Vec<_>>();
+                indices.sort_unstable();
+                indices.dedup();
+                assert_eq!(indices.len(), *neighbors);
+
+                // Neighborhood should be symmetric
+                for other in indices {
+                    assert!(state.neighbor_indices(other).any(|i| i == idx));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_volume_rule() {
+        let mut state = GameState::new((10, 10));
+        state.set_rule(Rule::Volume(VolumeRule::parse("4/4/5/M").unwrap()));
+        let (width, height, _) = volume::DEFAULT_VOLUME_SIZE;
+        assert_eq!(state.grid_size(), (width, height));
+
+        state.clear();
+        state.set_value_at_pos((3, 4), true);
+        let volume = state.active_volume().unwrap();
+        assert!(volume.is_alive((3, 4, volume.slice)));
+        assert!(!state.data.iter().any(|x| *x));
+
+        state.set_rule(Rule::Life);
+        assert_eq!(state.grid_size(), (10, 10));
+        assert!(state.active_volume().is_none());
+    }
+
+    #[test]
+    fn test_species_birth_takes_majority() {
+        let mut state = GameState::new((6, 6));
+        state.rule = Rule::Species(species::QUADLIFE);
+
+        // Vertical blinker with species 0, 1, 1
+        state.set_cell_at_pos((2, 1), true, 0);
+        state.set_cell_at_pos((2, 2), true, 1);
+        state.set_cell_at_pos((2, 3), true, 1);
+        state.tick();
+
+        let left = state.pos_to_index((1, 2));
+        let center = state.pos_to_index((2, 2));
+        let right = state.pos_to_index((3, 2));
+        assert!(state.data[left] && state.data[center] && state.data[right]);
+        assert_eq!(state.species[left], 1);
+        assert_eq!(state.species[center], 1);
+        assert_eq!(state.species[right], 1);
+
+        state.stats = GameStats::from_state(&state);
+        assert_eq!(state.stats.species, vec![0, 3, 0, 0]);
+    }
+
+    #[test]
+    fn test_species_kept_known_when_switching_rules() {
+        let mut state = GameState::new((6, 6));
+        state.set_rule(Rule::Species(species::QUADLIFE));
+        state.set_cell_at_pos((2, 1), true, 3);
+        state.set_cell_at_pos((2, 2), true, 2);
+        state.set_cell_at_pos((2, 3), true, 3);
+
+        state.set_rule(Rule::Species(species::IMMIGRATION));
+        assert!(state.species.iter().all(|species| *species < 2));
+        state.tick();
+        assert_eq!(state.species[state.pos_to_index((1, 2))], 1);
+    }
+
+    #[test]
+    fn test_margolus_billiard_ball_moves_diagonally() {
+        let mut state = GameState::new((8, 8));
+        state.rule = Rule::Margolus(margolus::BILLIARD_BALL_MACHINE);
+        state.set_value_at_pos((2, 2), true);
+
+        state.tick();
+        assert!(state.data[state.pos_to_index((3, 3))]);
+        state.tick();
+        assert!(state.data[state.pos_to_index((4, 4))]);
+        assert_eq!(state.data.iter().filter(|x| **x).count(), 1);
+    }
+
+    #[test]
+    fn test_cycle_with_calls_each_tick() {
+        let mut state = GameState::new((8, 8));
+        for x in 2..5 {
+            state.set_value_at_pos((x, 3), true);
+        }
+
+        let mut samples = vec![];
+        state.cycle_with(4, |state| {
+            samples.push((state.current_tick, state.stats.population))
+        });
+        assert_eq!(samples, vec![(1, 3), (2, 3), (3, 3), (4, 3)]);
+    }
+}
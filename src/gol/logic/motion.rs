@@ -0,0 +1,281 @@
+//! Spaceship motion
+//!
+//! Speeds use the usual notation: the displacement over one period as a
+//! fraction of the speed of light `c`, like `c/4 diagonal` for the glider or
+//! `(2,1)c/6` for oblique spaceships.
+
+use std::fmt;
+
+use super::{GameState, Pattern, Periodicity, Rule};
+
+/// Maximum period searched for selected objects
+pub const MAX_OBJECT_PERIOD: usize = 1024;
+
+/// Live cells processed by a call to `TrackedObject::analyze`, so large
+/// objects are analyzed over several frames
+pub const ANALYSIS_BUDGET: usize = 20_000;
+
+/// Periodic motion
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Motion {
+    pub period: usize,
+    /// Displacement over one period, with Y going down
+    pub displacement: (isize, isize),
+}
+
+/// Object selected for motion analysis
+#[derive(Clone, Debug)]
+pub struct TrackedObject {
+    pub pattern: Pattern,
+    /// Generation when the object was selected
+    pub generation: usize,
+    /// Motion, when the object is periodic
+    pub motion: Option<Motion>,
+    /// Current phase and generations run, while the analysis is in progress
+    analysis: Option<(Pattern, usize)>,
+}
+
+impl TrackedObject {
+    /// Select the object containing a live cell.
+    ///
+    /// Objects are run in isolation, so motion is only measured for the Game
+    /// of Life. The motion is found by calling `analyze` until it completes.
+    pub fn pick(state: &GameState, pos: (usize, usize)) -> Option<Self> {
+        let pattern = Pattern::component_at(state, pos)?;
+        let analysis = match state.rule {
+            Rule::Life => Some((pattern.clone(), 0)),
+            _ => None,
+        };
+
+        Some(Self {
+            pattern,
+            generation: state.current_tick,
+            motion: None,
+            analysis,
+        })
+    }
+
+    /// Get the number of generations run, while the analysis is in progress
+    pub fn analyzing(&self) -> Option<usize> {
+        self.analysis.as_ref().map(|(_, generations)| *generations)
+    }
+
+    /// Run the object until about `budget` live cells were processed, or its
+    /// motion is found
+    pub fn analyze(&mut self, budget: usize) {
+        let Some((mut current, mut generations)) = self.analysis.take() else {
+            return;
+        };
+
+        let mut processed = 0;
+        while processed < budget {
+            current = current.step();
+            generations += 1;
+            processed += current.population().max(1);
+
+            if current.is_empty() {
+                return;
+            }
+            if let Some((dx, dy)) = self.pattern.displacement_to(&current) {
+                self.motion = Some(Motion {
+                    period: generations,
+                    displacement: (dx as isize, dy as isize),
+                });
+                return;
+            }
+            if generations >= MAX_OBJECT_PERIOD {
+                return;
+            }
+        }
+
+        self.analysis = Some((current, generations));
+    }
+}
+
+fn gcd(a: usize, b: usize) -> usize {
+    if b == 0 {
+        a
+    } else {
+        gcd(b, a % b)
+    }
+}
+
+impl Motion {
+    /// Get the motion of a periodic grid of a given size.
+    ///
+    /// Displacements across the grid edges are unwrapped.
+    pub fn from_periodicity(periodicity: Periodicity, (width, height): (usize, usize)) -> Self {
+        let unwrap = |d: isize, size: usize| {
+            let size = size as isize;
+            if d > size / 2 {
+                d - size
+            } else if d < -size / 2 {
+                d + size
+            } else {
+                d
+            }
+        };
+
+        Self {
+            period: periodicity.period,
+            displacement: (
+                unwrap(periodicity.displacement.0, width),
+                unwrap(periodicity.displacement.1, height),
+            ),
+        }
+    }
+
+    /// Get the motion of an isolated Game of Life object, if periodic
+    pub fn from_pattern(pattern: &Pattern) -> Option<Self> {
+        pattern
+            .find_period(MAX_OBJECT_PERIOD)
+            .map(|(period, (dx, dy))| Self {
+                period,
+                displacement: (dx as isize, dy as isize),
+            })
+    }
+
+    pub fn is_moving(&self) -> bool {
+        self.displacement != (0, 0)
+    }
+
+    /// Get the direction, as `orthogonal`, `diagonal`, `oblique` or `stationary`
+    pub fn kind(&self) -> &'static str {
+        let (dx, dy) = (self.displacement.0.abs(), self.displacement.1.abs());
+        if dx == 0 && dy == 0 {
+            "stationary"
+        } else if dx == 0 || dy == 0 {
+            "orthogonal"
+        } else if dx == dy {
+            "diagonal"
+        } else {
+            "oblique"
+        }
+    }
+
+    /// Get the heading, as a compass direction with north at the top
+    pub fn heading(&self) -> &'static str {
+        match (self.displacement.0.signum(), self.displacement.1.signum()) {
+            (0, -1) => "north",
+            (1, -1) => "north-east",
+            (1, 0) => "east",
+            (1, 1) => "south-east",
+            (0, 1) => "south",
+            (-1, 1) => "south-west",
+            (-1, 0) => "west",
+            (-1, -1) => "north-west",
+            _ => "none",
+        }
+    }
+}
+
+impl fmt::Display for Motion {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let (dx, dy) = (
+            self.displacement.0.unsigned_abs(),
+            self.displacement.1.unsigned_abs(),
+        );
+        let period = self.period;
+
+        match self.kind() {
+            "stationary" => write!(f, "stationary"),
+            "oblique" => {
+                let d = gcd(gcd(dx, dy), period);
+                let (a, b) = (dx.max(dy) / d, dx.min(dy) / d);
+                write!(f, "({},{})c/{} oblique", a, b, period / d)
+            }
+            kind => {
+                let distance = dx.max(dy);
+                let d = gcd(distance, period);
+                let (distance, period) = (distance / d, period / d);
+                let speed = match (distance, period) {
+                    (1, 1) => "c".to_string(),
+                    (1, p) => format!("c/{}", p),
+                    (n, 1) => format!("{}c", n),
+                    (n, p) => format!("{}c/{}", n, p),
+                };
+                write!(f, "{} {}", speed, kind)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn motion(period: usize, displacement: (isize, isize)) -> Motion {
+        Motion {
+            period,
+            displacement,
+        }
+    }
+
+    #[test]
+    fn test_speed_notation() {
+        assert_eq!(motion(4, (1, 1)).to_string(), "c/4 diagonal");
+        assert_eq!(motion(4, (-2, 0)).to_string(), "c/2 orthogonal");
+        assert_eq!(motion(5, (0, 2)).to_string(), "2c/5 orthogonal");
+        assert_eq!(motion(6, (1, -2)).to_string(), "(2,1)c/6 oblique");
+        assert_eq!(motion(2, (0, 0)).to_string(), "stationary");
+    }
+
+    #[test]
+    fn test_heading() {
+        assert_eq!(motion(4, (1, 1)).heading(), "south-east");
+        assert_eq!(motion(4, (0, -2)).heading(), "north");
+        assert_eq!(motion(4, (-2, 0)).heading(), "west");
+    }
+
+    #[test]
+    fn test_unwrap() {
+        let periodicity = Periodicity {
+            period: 4,
+            start: 0,
+            displacement: (-19, 1),
+        };
+        let motion = Motion::from_periodicity(periodicity, (20, 20));
+        assert_eq!(motion.displacement, (1, 1));
+    }
+
+    #[test]
+    fn test_from_pattern() {
+        let lwss = Pattern::new(vec![
+            (1, 0),
+            (4, 0),
+            (0, 1),
+            (0, 2),
+            (4, 2),
+            (0, 3),
+            (1, 3),
+            (2, 3),
+            (3, 3),
+        ]);
+        let motion = Motion::from_pattern(&lwss).unwrap();
+        assert_eq!(motion.period, 4);
+        assert_eq!(motion.to_string(), "c/2 orthogonal");
+        assert_eq!(motion.heading(), "west");
+    }
+
+    #[test]
+    fn test_analysis_is_incremental() {
+        let mut state = GameState::new((16, 16));
+        for pos in [(1, 0), (2, 1), (0, 2), (1, 2), (2, 2)] {
+            state.set_value_at_pos(pos, true);
+        }
+
+        let mut tracked = TrackedObject::pick(&state, (2, 2)).unwrap();
+        assert_eq!(tracked.analyzing(), Some(0));
+
+        // Each call runs about one generation of the 5 cells glider
+        tracked.analyze(5);
+        assert_eq!(tracked.analyzing(), Some(1));
+        assert_eq!(tracked.motion, None);
+
+        for _ in 0..3 {
+            tracked.analyze(5);
+        }
+        assert_eq!(tracked.analyzing(), None);
+        assert_eq!(tracked.motion, Some(motion(4, (1, 1))));
+    }
+}
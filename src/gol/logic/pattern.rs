@@ -0,0 +1,293 @@
+//! Standalone patterns
+//!
+//! A pattern is a finite set of live cells on an infinite plane, used to
+//! study objects in isolation from the grid.
+
+use std::collections::HashMap;
+
+use super::GameState;
+
+/// Set of live cells, sorted by row then column
+#[derive(Clone, PartialEq, Eq, Hash, Debug, Default)]
+pub struct Pattern {
+    pub cells: Vec<(i32, i32)>,
+}
+
+impl Pattern {
+    pub fn new(mut cells: Vec<(i32, i32)>) -> Self {
+        cells.sort_unstable_by_key(|(x, y)| (*y, *x));
+        cells.dedup();
+        Self { cells }
+    }
+
+    /// Get all live cells from a game state
+    pub fn from_state(state: &GameState) -> Self {
+        Self::new(
+            state
+                .data
+                .iter()
+                .enumerate()
+                .filter(|(_, alive)| **alive)
+                .map(|(idx, _)| {
+                    let (x, y) = state.index_to_pos(idx);
+                    (x as i32, y as i32)
+                })
+                .collect(),
+        )
+    }
+
+    pub fn contains(&self, (x, y): (i32, i32)) -> bool {
+        self.cells
+            .binary_search_by_key(&(y, x), |(cx, cy)| (*cy, *cx))
+            .is_ok()
+    }
+
+    pub fn population(&self) -> usize {
+        self.cells.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.cells.is_empty()
+    }
+
+    /// Get the bounding box, as `(min_x, min_y, max_x, max_y)`
+    pub fn bounding_box(&self) -> Option<(i32, i32, i32, i32)> {
+        if self.cells.is_empty() {
+            return None;
+        }
+
+        Some(self.cells.iter().fold(
+            (i32::MAX, i32::MAX, i32::MIN, i32::MIN),
+            |(min_x, min_y, max_x, max_y), (x, y)| {
+                (min_x.min(*x), min_y.min(*y), max_x.max(*x), max_y.max(*y))
+            },
+        ))
+    }
+
+    /// Get the bounding box size
+    pub fn size(&self) -> (usize, usize) {
+        match self.bounding_box() {
+            Some((min_x, min_y, max_x, max_y)) => {
+                ((max_x - min_x + 1) as usize, (max_y - min_y + 1) as usize)
+            }
+            None => (0, 0),
+        }
+    }
+
+    pub fn translate(&self, (dx, dy): (i32, i32)) -> Self {
+        Self::new(self.cells.iter().map(|(x, y)| (x + dx, y + dy)).collect())
+    }
+
+    /// Move the pattern so its bounding box starts at (0, 0)
+    pub fn normalize(&self) -> Self {
+        match self.bounding_box() {
+            Some((min_x, min_y, _, _)) => self.translate((-min_x, -min_y)),
+            None => self.clone(),
+        }
+    }
+
+    /// Apply one of the 8 rotations and reflections, from 0 to 7
+    pub fn transform(&self, transform: usize) -> Self {
+        let cells = self
+            .cells
+            .iter()
+            .map(|(x, y)| {
+                let (x, y) = if transform & 4 != 0 {
+                    (*y, *x)
+                } else {
+                    (*x, *y)
+                };
+                let x = if transform & 1 != 0 { -x } else { x };
+                let y = if transform & 2 != 0 { -y } else { y };
+                (x, y)
+            })
+            .collect();
+
+        Self::new(cells).normalize()
+    }
+
+    /// Get all the 8 rotations and reflections, normalized
+    pub fn transforms(&self) -> Vec<Self> {
+        (0..8).map(|t| self.transform(t)).collect()
+    }
+
+    /// Run one generation of the Game of Life
+    pub fn step(&self) -> Self {
+        let mut counts: HashMap<(i32, i32), usize> = HashMap::new();
+        for (x, y) in &self.cells {
+            for dy in -1..=1 {
+                for dx in -1..=1 {
+                    if dx != 0 || dy != 0 {
+                        *counts.entry((x + dx, y + dy)).or_insert(0) += 1;
+                    }
+                }
+            }
+        }
+
+        Self::new(
+            counts
+                .into_iter()
+                .filter(|(pos, count)| *count == 3 || (*count == 2 && self.contains(*pos)))
+                .map(|(pos, _)| pos)
+                .collect(),
+        )
+    }
+
+    /// Find the period and displacement of the pattern, up to `max_period`
+    /// generations
+    pub fn find_period(&self, max_period: usize) -> Option<(usize, (i32, i32))> {
+        self.bounding_box()?;
+        let mut current = self.clone();
+
+        for period in 1..=max_period {
+            current = current.step();
+            if current.is_empty() {
+                return None;
+            }
+            if let Some(displacement) = self.displacement_to(&current) {
+                return Some((period, displacement));
+            }
+        }
+
+        None
+    }
+
+    /// Get the displacement to another pattern, when it has the same shape
+    pub fn displacement_to(&self, other: &Pattern) -> Option<(i32, i32)> {
+        let (min_x, min_y, _, _) = self.bounding_box()?;
+        let (other_x, other_y, _, _) = other.bounding_box()?;
+        let displacement = (other_x - min_x, other_y - min_y);
+
+        (other.population() == self.population() && self.translate(displacement) == *other)
+            .then_some(displacement)
+    }
+
+    /// Split live cells of a game state into 8-connected components.
+    ///
+    /// Components crossing the grid edges are unwrapped.
+    pub fn components(state: &GameState) -> Vec<Self> {
+        let mut visited = vec![false; state.size()];
+
+        (0..state.size())
+            .filter_map(|start| Self::flood_fill(state, start, &mut visited))
+            .collect()
+    }
+
+    /// Get the 8-connected component containing a live cell
+    pub fn component_at(state: &GameState, pos: (usize, usize)) -> Option<Self> {
+        let mut visited = vec![false; state.size()];
+        Self::flood_fill(state, state.pos_to_index(pos), &mut visited)
+    }
+
+    fn flood_fill(state: &GameState, start: usize, visited: &mut [bool]) -> Option<Self> {
+        if !state.data[start] || visited[start] {
+            return None;
+        }
+
+        let (sx, sy) = state.index_to_pos(start);
+        let mut cells = vec![];
+        let mut stack = vec![(start, (sx as i32, sy as i32))];
+        visited[start] = true;
+
+        while let Some((idx, (ux, uy))) = stack.pop() {
+            cells.push((ux, uy));
+            let (x, y) = state.index_to_pos(idx);
+
+            for dy in -1..=1 {
+                for dx in -1..=1 {
+                    if dx == 0 && dy == 0 {
+                        continue;
+                    }
+
+                    let nx = (x as isize + dx).rem_euclid(state.width as isize) as usize;
+                    let ny = (y as isize + dy).rem_euclid(state.height as isize) as usize;
+                    let nidx = state.pos_to_index((nx, ny));
+                    if state.data[nidx] && !visited[nidx] {
+                        visited[nidx] = true;
+                        stack.push((nidx, (ux + dx as i32, uy + dy as i32)));
+                    }
+                }
+            }
+        }
+
+        Some(Self::new(cells))
+    }
+}
+
+impl GameState {
+    /// Paste the live cells of a pattern, centered on a position
+    pub fn paste_pattern(&mut self, pattern: &Pattern, (x, y): (usize, usize)) {
+        let pattern = pattern.normalize();
+        let (width, height) = pattern.size();
+        let (grid_width, grid_height) = self.grid_size();
+
+        for (cx, cy) in &pattern.cells {
+            let px = (x as i32 + cx - width as i32 / 2).rem_euclid(grid_width as i32);
+            let py = (y as i32 + cy - height as i32 / 2).rem_euclid(grid_height as i32);
+            self.set_cell_at_pos((px as usize, py as usize), true, 0);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn glider() -> Pattern {
+        Pattern::new(vec![(1, 0), (2, 1), (0, 2), (1, 2), (2, 2)])
+    }
+
+    #[test]
+    fn test_transforms() {
+        let transforms = glider().transforms();
+        assert_eq!(transforms.len(), 8);
+        for pattern in &transforms {
+            assert_eq!(pattern.population(), 5);
+            assert_eq!(pattern.size(), (3, 3));
+        }
+    }
+
+    #[test]
+    fn test_find_period() {
+        let block = Pattern::new(vec![(0, 0), (1, 0), (0, 1), (1, 1)]);
+        assert_eq!(block.find_period(10), Some((1, (0, 0))));
+
+        let blinker = Pattern::new(vec![(0, 0), (1, 0), (2, 0)]);
+        assert_eq!(blinker.find_period(10), Some((2, (0, 0))));
+
+        assert_eq!(glider().find_period(10), Some((4, (1, 1))));
+
+        let r_pentomino = Pattern::new(vec![(1, 0), (2, 0), (0, 1), (1, 1), (1, 2)]);
+        assert_eq!(r_pentomino.find_period(10), None);
+    }
+
+    #[test]
+    fn test_components() {
+        let mut state = GameState::new((10, 10));
+        // Block crossing the corner
+        for pos in &[(9, 9), (0, 9), (9, 0), (0, 0)] {
+            state.set_value_at_pos(*pos, true);
+        }
+        // Blinker
+        for pos in &[(4, 4), (5, 4), (6, 4)] {
+            state.set_value_at_pos(*pos, true);
+        }
+
+        let components = Pattern::components(&state);
+        assert_eq!(components.len(), 2);
+        for component in &components {
+            let size = component.size();
+            assert!(size == (2, 2) || size == (3, 1));
+        }
+
+        assert_eq!(
+            Pattern::component_at(&state, (5, 4)).unwrap().size(),
+            (3, 1)
+        );
+        assert_eq!(
+            Pattern::component_at(&state, (0, 9)).unwrap().size(),
+            (2, 2)
+        );
+        assert!(Pattern::component_at(&state, (5, 5)).is_none());
+    }
+}
@@ -0,0 +1,226 @@
+//! Pattern files
+//!
+//! Patterns are read and written in the RLE format, and read from the
+//! plaintext `.cells` format:
+//!
+//! ```text
+//! #N Glider
+//! x = 3, y = 3, rule = B3/S23
+//! bo$2bo$3o!
+//! ```
+
+use std::fmt::Write;
+
+use super::Pattern;
+
+/// Maximum RLE line length
+const LINE_LENGTH: usize = 70;
+
+/// Pattern read from a file
+#[derive(Clone, Debug)]
+pub struct PatternFile {
+    pub pattern: Pattern,
+    /// Rule given in the RLE header
+    pub rule: Option<String>,
+}
+
+impl PatternFile {
+    /// Parse a RLE or plaintext pattern
+    pub fn parse(contents: &str) -> Result<Self, String> {
+        let is_rle = contents
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.starts_with('!'))
+            .any(|line| line.starts_with("x ") || line.starts_with("x=") || line.ends_with('!'));
+
+        if is_rle {
+            Self::parse_rle(contents)
+        } else {
+            Ok(Self::parse_plaintext(contents))
+        }
+    }
+
+    /// Parse a RLE pattern
+    pub fn parse_rle(contents: &str) -> Result<Self, String> {
+        let mut rule = None;
+        let mut cells = vec![];
+        let (mut x, mut y) = (0, 0);
+        let mut count = String::new();
+
+        let lines = contents
+            .lines()
+            .map(str::trim)
+            .filter(|l| !l.starts_with('#'));
+        'lines: for line in lines {
+            if line.starts_with('x') {
+                rule = line
+                    .split(',')
+                    .filter_map(|item| item.split_once('='))
+                    .find(|(key, _)| key.trim() == "rule")
+                    .map(|(_, value)| value.trim().to_string());
+                continue;
+            }
+
+            for c in line.chars() {
+                if c.is_ascii_digit() {
+                    count.push(c);
+                    continue;
+                }
+
+                let run = if count.is_empty() {
+                    1
+                } else {
+                    count
+                        .parse::<i32>()
+                        .map_err(|_| format!("Invalid run count: {}", count))?
+                };
+                count.clear();
+
+                match c {
+                    'b' | '.' => x += run,
+                    '$' => {
+                        x = 0;
+                        y += run;
+                    }
+                    '!' => break 'lines,
+                    c if c.is_ascii_alphabetic() => {
+                        cells.extend((x..x + run).map(|cx| (cx, y)));
+                        x += run;
+                    }
+                    c if c.is_whitespace() => (),
+                    c => return Err(format!("Invalid RLE character: {}", c)),
+                }
+            }
+        }
+
+        Ok(Self {
+            pattern: Pattern::new(cells),
+            rule,
+        })
+    }
+
+    /// Parse a plaintext pattern, with `O` for live cells
+    pub fn parse_plaintext(contents: &str) -> Self {
+        let cells = contents
+            .lines()
+            .filter(|line| !line.starts_with('!'))
+            .enumerate()
+            .flat_map(|(y, line)| {
+                line.chars()
+                    .enumerate()
+                    .filter(|(_, c)| *c == 'O' || *c == '*')
+                    .map(move |(x, _)| (x as i32, y as i32))
+            })
+            .collect();
+
+        Self {
+            pattern: Pattern::new(cells),
+            rule: None,
+        }
+    }
+
+    /// Get the RLE representation of a pattern
+    pub fn to_rle(pattern: &Pattern, rule: &str) -> String {
+        let pattern = pattern.normalize();
+        let (width, height) = pattern.size();
+        let mut output = String::new();
+        writeln!(output, "x = {}, y = {}, rule = {}", width, height, rule).unwrap();
+
+        // Runs of (count, tag), without trailing dead cells
+        let mut runs: Vec<(usize, char)> = vec![];
+        let push = |runs: &mut Vec<(usize, char)>, count: usize, tag: char| {
+            if count == 0 {
+                return;
+            }
+            match runs.last_mut() {
+                Some((last_count, last_tag)) if *last_tag == tag => *last_count += count,
+                _ => runs.push((count, tag)),
+            }
+        };
+
+        let mut cells = pattern.cells.iter().peekable();
+        let mut empty_rows = 0;
+        for y in 0..height as i32 {
+            let mut x = 0;
+            let mut row_empty = true;
+            while let Some((cx, _)) = cells.next_if(|(_, cy)| *cy == y) {
+                if row_empty {
+                    push(&mut runs, empty_rows, '$');
+                    empty_rows = 0;
+                    row_empty = false;
+                }
+                push(&mut runs, (cx - x) as usize, 'b');
+                push(&mut runs, 1, 'o');
+                x = cx + 1;
+            }
+            empty_rows += 1;
+        }
+        runs.push((1, '!'));
+
+        let mut line = String::new();
+        for (count, tag) in runs {
+            let item = match count {
+                1 => tag.to_string(),
+                n => format!("{}{}", n, tag),
+            };
+            if line.len() + item.len() > LINE_LENGTH {
+                writeln!(output, "{}", line).unwrap();
+                line.clear();
+            }
+            line.push_str(&item);
+        }
+        writeln!(output, "{}", line).unwrap();
+
+        output
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn glider() -> Pattern {
+        Pattern::new(vec![(1, 0), (2, 1), (0, 2), (1, 2), (2, 2)])
+    }
+
+    #[test]
+    fn test_parse_rle() {
+        let file =
+            PatternFile::parse("#N Glider\nx = 3, y = 3, rule = B3/S23\nbo$2bo$3o!\n").unwrap();
+        assert_eq!(file.pattern, glider());
+        assert_eq!(file.rule.as_deref(), Some("B3/S23"));
+
+        let file = PatternFile::parse("x = 1, y = 3\no2$o!").unwrap();
+        assert_eq!(file.pattern, Pattern::new(vec![(0, 0), (0, 2)]));
+        assert_eq!(file.rule, None);
+
+        assert!(PatternFile::parse("x = 1, y = 1\no?!").is_err());
+    }
+
+    #[test]
+    fn test_parse_plaintext() {
+        let file = PatternFile::parse("!Name: Glider\n.O.\n..O\nOOO\n").unwrap();
+        assert_eq!(file.pattern, glider());
+    }
+
+    #[test]
+    fn test_to_rle() {
+        assert_eq!(
+            PatternFile::to_rle(&glider(), "B3/S23"),
+            "x = 3, y = 3, rule = B3/S23\nbo$2bo$3o!\n"
+        );
+
+        let sparse = Pattern::new(vec![(0, 0), (5, 0), (2, 3)]);
+        let rle = PatternFile::to_rle(&sparse, "B3/S23");
+        assert_eq!(rle, "x = 6, y = 4, rule = B3/S23\no4bo3$2bo!\n");
+        assert_eq!(PatternFile::parse(&rle).unwrap().pattern, sparse);
+    }
+
+    #[test]
+    fn test_long_lines() {
+        let cells = (0..200).step_by(2).map(|x| (x, 0)).collect();
+        let rle = PatternFile::to_rle(&Pattern::new(cells), "B3/S23");
+        assert!(rle.lines().all(|line| line.len() <= LINE_LENGTH));
+        assert_eq!(PatternFile::parse(&rle).unwrap().pattern.population(), 100);
+    }
+}
@@ -0,0 +1,449 @@
+//! Oscillation and still life detection
+//!
+//! Each generation is hashed twice: once as a whole grid, and once relative
+//! to the bounding box of live cells, to catch translating patterns. When a
+//! hash is seen again, the grid is run again for the period on a copy, to
+//! make sure it is periodic and not a hash collision.
+//!
+//! Detection costs a pass over the grid each generation, so it only runs
+//! when the game state asks for it.
+//!
+//! The grid is a torus, so the bounding box starts after the largest empty
+//! gap on each axis, and patterns crossing the edges keep their shape.
+
+use std::collections::hash_map::DefaultHasher;
+use std::collections::VecDeque;
+use std::hash::{Hash, Hasher};
+
+use super::{GameState, MargolusRule, Rule};
+
+/// Number of generations remembered, bounding the detectable period
+const DEFAULT_CAPACITY: usize = 1024;
+
+/// Detected periodicity
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Periodicity {
+    /// Period, in generations (1 for still lifes)
+    pub period: usize,
+    /// First generation of the cycle
+    pub start: usize,
+    /// Displacement over one period
+    pub displacement: (isize, isize),
+}
+
+impl Periodicity {
+    pub fn is_still(&self) -> bool {
+        self.period == 1 && self.displacement == (0, 0)
+    }
+
+    pub fn is_moving(&self) -> bool {
+        self.displacement != (0, 0)
+    }
+}
+
+/// Generation signature
+#[derive(Clone)]
+struct Signature {
+    tick: usize,
+    hash: u64,
+    shape_hash: u64,
+    origin: (usize, usize),
+    /// Grid size
+    size: (usize, usize),
+}
+
+/// Get the start of the occupied part of a wrapping axis: the first occupied
+/// position after the largest empty gap
+fn span_start(occupied: &[bool]) -> usize {
+    let size = occupied.len();
+    let first = match occupied.iter().position(|o| *o) {
+        Some(first) => first,
+        None => return 0,
+    };
+
+    // Go around once, the last gap wraps back to the first position
+    let (mut start, mut largest_gap, mut gap) = (first, 0, 0);
+    for i in 1..=size {
+        let pos = (first + i) % size;
+        if occupied[pos] {
+            if gap > largest_gap {
+                largest_gap = gap;
+                start = pos;
+            }
+            gap = 0;
+        } else {
+            gap += 1;
+        }
+    }
+
+    start
+}
+
+/// Wrap a displacement on a wrapping axis to `(-size/2, size/2]`
+fn wrap_displacement(d: isize, size: usize) -> isize {
+    let size = size as isize;
+    let d = d.rem_euclid(size);
+    if d > size / 2 {
+        d - size
+    } else {
+        d
+    }
+}
+
+/// Get the start of the bounding box of live cells, on the torus
+fn origin(state: &GameState) -> (usize, usize) {
+    let mut columns = vec![false; state.width];
+    let mut rows = vec![false; state.height];
+
+    for (idx, alive) in state.data.iter().enumerate() {
+        if *alive {
+            let (x, y) = state.index_to_pos(idx);
+            columns[x] = true;
+            rows[y] = true;
+        }
+    }
+
+    (span_start(&columns), span_start(&rows))
+}
+
+/// Get the cells set in `data` relative to an origin, with their species,
+/// sorted
+fn relative_cells(
+    state: &GameState,
+    data: &[bool],
+    origin: (usize, usize),
+) -> Vec<(usize, usize, u8)> {
+    let (width, height) = (state.width, state.height);
+    let mut cells = data
+        .iter()
+        .enumerate()
+        .filter(|(_, alive)| **alive)
+        .map(|(idx, _)| {
+            let (x, y) = state.index_to_pos(idx);
+            (
+                (x + width - origin.0) % width,
+                (y + height - origin.1) % height,
+                state.species[idx],
+            )
+        })
+        .collect::<Vec<_>>();
+    cells.sort_unstable();
+    cells
+}
+
+/// Check if two generations are the same, exactly or up to a translation
+fn same_generation(a: &GameState, b: &GameState, translated: bool) -> bool {
+    let (a_origin, b_origin) = match translated {
+        true => (origin(a), origin(b)),
+        false => ((0, 0), (0, 0)),
+    };
+    let same = |a_data: &[bool], b_data: &[bool]| match translated {
+        true => relative_cells(a, a_data, a_origin) == relative_cells(b, b_data, b_origin),
+        false => a_data == b_data,
+    };
+
+    let hidden_state = match a.rule {
+        Rule::Margolus(_) => {
+            MargolusRule::offset_for_tick(a.current_tick)
+                == MargolusRule::offset_for_tick(b.current_tick)
+        }
+        Rule::SecondOrder(_) => same(&a.previous, &b.previous),
+        Rule::Species(_) if !translated => a.species == b.species,
+        _ => true,
+    };
+
+    hidden_state && same(&a.data, &b.data)
+}
+
+impl Signature {
+    fn from_state(state: &GameState) -> Option<Self> {
+        if let Rule::Volume(_) = state.rule {
+            return None;
+        }
+
+        let mut hasher = DefaultHasher::new();
+        let mut shape_hasher = DefaultHasher::new();
+        let (width, height) = (state.width, state.height);
+        state.data.hash(&mut hasher);
+        let min = origin(state);
+
+        // Hash live cells relative to the bounding box, with their species
+        relative_cells(state, &state.data, min).hash(&mut shape_hasher);
+
+        // Rule-specific hidden state
+        match state.rule {
+            Rule::Margolus(_) => {
+                let offset = MargolusRule::offset_for_tick(state.current_tick);
+                offset.hash(&mut hasher);
+                offset.hash(&mut shape_hasher);
+            }
+            Rule::SecondOrder(_) => {
+                state.previous.hash(&mut hasher);
+                state.previous.hash(&mut shape_hasher);
+            }
+            Rule::Species(_) => state.species.hash(&mut hasher),
+            _ => (),
+        }
+
+        Some(Self {
+            tick: state.current_tick,
+            hash: hasher.finish(),
+            shape_hash: shape_hasher.finish(),
+            origin: min,
+            size: (width, height),
+        })
+    }
+}
+
+/// Cycle detector
+#[derive(Clone)]
+pub struct CycleDetector {
+    signatures: VecDeque<Signature>,
+    capacity: usize,
+    edits: usize,
+    rule: Option<Rule>,
+    pub result: Option<Periodicity>,
+}
+
+impl CycleDetector {
+    pub fn new() -> Self {
+        Self {
+            signatures: VecDeque::new(),
+            capacity: DEFAULT_CAPACITY,
+            edits: 0,
+            rule: None,
+            result: None,
+        }
+    }
+
+    pub fn reset(&mut self) {
+        self.signatures.clear();
+        self.result = None;
+        self.rule = None;
+    }
+
+    /// Check if the detector follows the state without interruption
+    pub fn is_tracking(&self, state: &GameState) -> bool {
+        self.rule == Some(state.rule)
+            && self.edits == state.edits
+            && self.signatures.back().map(|s| s.tick) == Some(state.current_tick)
+    }
+
+    /// Observe a generation, returns true when a new periodicity is detected
+    pub fn observe(&mut self, state: &GameState) -> bool {
+        let signature = match Signature::from_state(state) {
+            Some(signature) => signature,
+            None => return false,
+        };
+
+        self.rule = Some(state.rule);
+        self.edits = state.edits;
+        let detected = self
+            .detect(&signature)
+            .filter(|detected| self.is_known(detected) || Self::verify(state, detected));
+        self.signatures.push_back(signature);
+        if self.signatures.len() > self.capacity {
+            self.signatures.pop_front();
+        }
+
+        match detected {
+            Some(detected) => {
+                let is_new = !self.is_known(&detected);
+                if is_new {
+                    self.result = Some(detected);
+                }
+                is_new
+            }
+            None => {
+                self.result = None;
+                false
+            }
+        }
+    }
+
+    /// Check if a periodicity was already detected
+    fn is_known(&self, detected: &Periodicity) -> bool {
+        self.result.is_some_and(|result| {
+            result.period == detected.period && result.displacement == detected.displacement
+        })
+    }
+
+    /// Run a copy of the state for the period, to rule out hash collisions
+    fn verify(state: &GameState, detected: &Periodicity) -> bool {
+        let mut copy = state.clone();
+        copy.auto_pause = false;
+        copy.detect_cycles = false;
+        for _ in 0..detected.period {
+            copy.tick();
+        }
+
+        same_generation(state, &copy, detected.is_moving())
+    }
+
+    /// Find the shortest period, preferring exact repetitions
+    fn detect(&self, signature: &Signature) -> Option<Periodicity> {
+        // Exact repetition
+        let exact = self
+            .signatures
+            .iter()
+            .rev()
+            .find(|s| s.hash == signature.hash)
+            .map(|previous| Periodicity {
+                period: signature.tick - previous.tick,
+                start: previous.tick,
+                displacement: (0, 0),
+            });
+
+        // Translated repetition
+        let translated = self
+            .signatures
+            .iter()
+            .rev()
+            .find(|s| s.shape_hash == signature.shape_hash)
+            .map(|previous| Periodicity {
+                period: signature.tick - previous.tick,
+                start: previous.tick,
+                displacement: (
+                    wrap_displacement(
+                        signature.origin.0 as isize - previous.origin.0 as isize,
+                        signature.size.0,
+                    ),
+                    wrap_displacement(
+                        signature.origin.1 as isize - previous.origin.1 as isize,
+                        signature.size.1,
+                    ),
+                ),
+            });
+
+        match (exact, translated) {
+            (Some(exact), Some(translated)) if translated.period < exact.period => Some(translated),
+            (Some(exact), _) => Some(exact),
+            (None, translated) => translated,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::GameState;
+    use super::{CycleDetector, Periodicity};
+
+    fn run_until_detected(state: &mut GameState, max_ticks: usize) {
+        state.detect_cycles = true;
+        for _ in 0..max_ticks {
+            state.tick();
+            if state.cycle_detector.result.is_some() {
+                return;
+            }
+        }
+    }
+
+    #[test]
+    fn test_still_life() {
+        let mut state = GameState::new((10, 10));
+        for pos in &[(2, 2), (3, 2), (2, 3), (3, 3)] {
+            state.set_value_at_pos(*pos, true);
+        }
+
+        run_until_detected(&mut state, 10);
+        let result = state.cycle_detector.result.unwrap();
+        assert!(result.is_still());
+        assert_eq!(result.start, 0);
+    }
+
+    #[test]
+    fn test_oscillator() {
+        let mut state = GameState::new((10, 10));
+        // Needs a few generations to become a blinker
+        for pos in &[(3, 3), (4, 3), (5, 3), (4, 4)] {
+            state.set_value_at_pos(*pos, true);
+        }
+
+        run_until_detected(&mut state, 50);
+        let result = state.cycle_detector.result.unwrap();
+        assert_eq!(result.period, 2);
+        assert_eq!(result.displacement, (0, 0));
+        assert!(result.start > 0);
+    }
+
+    #[test]
+    fn test_spaceship() {
+        let mut state = GameState::new((16, 16));
+        for pos in &[(2, 1), (3, 2), (1, 3), (2, 3), (3, 3)] {
+            state.set_value_at_pos(*pos, true);
+        }
+
+        run_until_detected(&mut state, 10);
+        let result = state.cycle_detector.result.unwrap();
+        assert_eq!(result.period, 4);
+        assert_eq!(result.displacement, (1, 1));
+        assert_eq!(result.start, 0);
+    }
+
+    #[test]
+    fn test_spaceship_across_edges() {
+        let mut state = GameState::new((16, 16));
+        state.auto_pause = true;
+        // Glider heading south-east, about to cross the corner
+        for pos in &[(12, 11), (13, 12), (11, 13), (12, 13), (13, 13)] {
+            state.set_value_at_pos(*pos, true);
+        }
+
+        state.cycle(100);
+        assert!(!state.running);
+        let result = state.cycle_detector.result.unwrap();
+        assert_eq!(result.period, 4);
+        assert_eq!(result.displacement, (1, 1));
+
+        // The same periodicity is kept while the glider wraps around
+        state.running = true;
+        state.cycle(64);
+        assert!(state.running);
+        assert_eq!(state.cycle_detector.result.unwrap().displacement, (1, 1));
+    }
+
+    #[test]
+    fn test_detection_is_opt_in() {
+        let mut state = GameState::new((10, 10));
+        for pos in &[(2, 2), (3, 2), (2, 3), (3, 3)] {
+            state.set_value_at_pos(*pos, true);
+        }
+
+        state.cycle(10);
+        assert!(state.cycle_detector.result.is_none());
+        assert!(state.stats.periodicity.is_none());
+    }
+
+    #[test]
+    fn test_verify() {
+        let mut state = GameState::new((10, 10));
+        // Blinker
+        for pos in &[(3, 4), (4, 4), (5, 4)] {
+            state.set_value_at_pos(*pos, true);
+        }
+        let periodicity = |period| Periodicity {
+            period,
+            start: 0,
+            displacement: (0, 0),
+        };
+
+        assert!(!CycleDetector::verify(&state, &periodicity(1)));
+        assert!(CycleDetector::verify(&state, &periodicity(2)));
+        // The state itself is not run
+        assert_eq!(state.current_tick, 0);
+    }
+
+    #[test]
+    fn test_auto_pause() {
+        let mut state = GameState::new((10, 10));
+        state.auto_pause = true;
+        for pos in &[(2, 2), (3, 2), (2, 3), (3, 3)] {
+            state.set_value_at_pos(*pos, true);
+        }
+
+        state.cycle(10);
+        assert!(!state.running);
+        assert_eq!(state.current_tick, 1);
+        assert!(state.stats.periodicity.is_some());
+    }
+}
@@ -0,0 +1,225 @@
+//! Population history
+//!
+//! Stats are sampled after each generation and kept in a rolling
+//! window, to be plotted or exported as CSV or JSON.
+
+use std::collections::VecDeque;
+use std::fmt::Write;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use super::GameState;
+
+/// Default number of samples kept
+pub const DEFAULT_CAPACITY: usize = 100_000;
+
+/// Stats of one generation
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Sample {
+    pub generation: usize,
+    pub population: usize,
+    pub births: usize,
+    pub deaths: usize,
+    pub moving: usize,
+    pub stopped: usize,
+}
+
+/// Rolling population history
+#[derive(Clone)]
+pub struct PopulationHistory {
+    samples: VecDeque<Sample>,
+    capacity: usize,
+    edits: usize,
+}
+
+impl Default for PopulationHistory {
+    fn default() -> Self {
+        Self::new(DEFAULT_CAPACITY)
+    }
+}
+
+impl PopulationHistory {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            samples: VecDeque::new(),
+            capacity: capacity.max(1),
+            edits: 0,
+        }
+    }
+
+    /// Record the current stats of a game state.
+    ///
+    /// Samples after the current generation are dropped when going back in
+    /// time, and the last sample is replaced when the grid was edited.
+    pub fn record(&mut self, state: &GameState) {
+        let generation = state.current_tick;
+        while self
+            .samples
+            .back()
+            .is_some_and(|sample| sample.generation > generation)
+        {
+            self.samples.pop_back();
+        }
+
+        if let Some(last) = self.samples.back() {
+            if last.generation == generation {
+                if self.edits == state.edits {
+                    return;
+                }
+                self.samples.pop_back();
+            }
+        }
+
+        let stats = &state.stats;
+        self.edits = state.edits;
+        self.samples.push_back(Sample {
+            generation,
+            population: stats.population,
+            births: stats.births,
+            deaths: stats.deaths,
+            moving: stats.moving,
+            stopped: stats.stopped,
+        });
+        if self.samples.len() > self.capacity {
+            self.samples.pop_front();
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.samples.clear();
+    }
+
+    pub fn len(&self) -> usize {
+        self.samples.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.samples.is_empty()
+    }
+
+    pub fn samples(&self) -> impl Iterator<Item = &Sample> {
+        self.samples.iter()
+    }
+
+    /// Get a value for the last `count` samples, to be plotted
+    pub fn series<F: Fn(&Sample) -> usize>(&self, count: usize, value: F) -> Vec<f32> {
+        self.samples
+            .iter()
+            .skip(self.samples.len().saturating_sub(count))
+            .map(|sample| value(sample) as f32)
+            .collect()
+    }
+
+    /// Get the full series as CSV
+    pub fn to_csv(&self) -> String {
+        let mut output = String::from("generation,population,births,deaths,moving,stopped\n");
+        for s in &self.samples {
+            writeln!(
+                output,
+                "{},{},{},{},{},{}",
+                s.generation, s.population, s.births, s.deaths, s.moving, s.stopped
+            )
+            .unwrap();
+        }
+
+        output
+    }
+
+    /// Get the full series as a JSON array of samples
+    pub fn to_json(&self) -> String {
+        let samples = self
+            .samples
+            .iter()
+            .map(|s| {
+                format!(
+                    "{{\"generation\":{},\"population\":{},\"births\":{},\"deaths\":{},\"moving\":{},\"stopped\":{}}}",
+                    s.generation, s.population, s.births, s.deaths, s.moving, s.stopped
+                )
+            })
+            .collect::<Vec<_>>();
+
+        format!("[{}]\n", samples.join(",\n"))
+    }
+
+    /// Write the full series to a CSV file
+    pub fn write_csv<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        fs::write(path, self.to_csv())
+    }
+
+    /// Write the full series to a JSON file
+    pub fn write_json<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        fs::write(path, self.to_json())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn blinker() -> GameState {
+        let mut state = GameState::new((8, 8));
+        for pos in &[(2, 3), (3, 3), (4, 3)] {
+            state.set_value_at_pos(*pos, true);
+        }
+        state
+    }
+
+    #[test]
+    fn test_record() {
+        let mut state = blinker();
+        let mut history = PopulationHistory::new(3);
+
+        for _ in 0..5 {
+            state.cycle(1);
+            history.record(&state);
+            // Same generation is not recorded twice
+            history.record(&state);
+        }
+
+        assert_eq!(history.len(), 3);
+        let samples = history.samples().collect::<Vec<_>>();
+        assert_eq!(samples[0].generation, 3);
+        assert_eq!(samples[2].generation, 5);
+        for sample in samples {
+            assert_eq!(sample.population, 3);
+            assert_eq!(sample.births, 2);
+            assert_eq!(sample.deaths, 2);
+        }
+    }
+
+    #[test]
+    fn test_going_back() {
+        let mut state = blinker();
+        let mut history = PopulationHistory::default();
+        let start = state.snapshot();
+
+        state.cycle(1);
+        history.record(&state);
+        state.cycle(1);
+        history.record(&state);
+
+        state.restore(&start);
+        history.record(&state);
+        assert_eq!(history.len(), 1);
+        assert_eq!(history.samples().next().unwrap().generation, 0);
+    }
+
+    #[test]
+    fn test_export() {
+        let mut state = blinker();
+        let mut history = PopulationHistory::default();
+        state.cycle(1);
+        history.record(&state);
+
+        assert_eq!(
+            history.to_csv(),
+            "generation,population,births,deaths,moving,stopped\n1,3,2,2,3,0\n"
+        );
+        assert_eq!(
+            history.to_json(),
+            "[{\"generation\":1,\"population\":3,\"births\":2,\"deaths\":2,\"moving\":3,\"stopped\":0}]\n"
+        );
+        assert_eq!(history.series(10, |s| s.population), vec![3.0]);
+    }
+}
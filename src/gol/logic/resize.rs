@@ -0,0 +1,227 @@
+//! Grid resizing
+//!
+//! Cells are kept when the grid is resized, at the same distance from the
+//! anchor: growing adds dead cells on the opposite sides, shrinking crops
+//! them. The 3D grid keeps its size.
+//!
+//! Hexagonal and triangular grids keep even sides, and cells move by an even
+//! number of rows and columns, so the shape of the cells is kept. Margolus
+//! grids keep even sides too, so the blocks tile the grid, and cells stay in
+//! the same block position.
+
+use std::fmt;
+
+use super::{GameState, GameStats, Rule, Tiling};
+
+/// Part of the grid which stays in place when resizing
+#[derive(Clone, Copy, PartialEq, Debug, Default)]
+pub enum Anchor {
+    TopLeft,
+    TopRight,
+    #[default]
+    Center,
+    BottomLeft,
+    BottomRight,
+}
+
+impl Anchor {
+    pub fn all() -> [Self; 5] {
+        [
+            Self::TopLeft,
+            Self::TopRight,
+            Self::Center,
+            Self::BottomLeft,
+            Self::BottomRight,
+        ]
+    }
+
+    /// Get the shift of the cells when resizing, as `(dx, dy)`
+    fn offset(self, old_size: (usize, usize), new_size: (usize, usize)) -> (isize, isize) {
+        let dx = new_size.0 as isize - old_size.0 as isize;
+        let dy = new_size.1 as isize - old_size.1 as isize;
+
+        match self {
+            Self::TopLeft => (0, 0),
+            Self::TopRight => (dx, 0),
+            Self::Center => (dx.div_euclid(2), dy.div_euclid(2)),
+            Self::BottomLeft => (0, dy),
+            Self::BottomRight => (dx, dy),
+        }
+    }
+}
+
+impl fmt::Display for Anchor {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::TopLeft => write!(f, "Top left"),
+            Self::TopRight => write!(f, "Top right"),
+            Self::Center => write!(f, "Center"),
+            Self::BottomLeft => write!(f, "Bottom left"),
+            Self::BottomRight => write!(f, "Bottom right"),
+        }
+    }
+}
+
+impl GameState {
+    /// Check if the rule needs even grid sides
+    fn needs_even_sides(&self) -> bool {
+        self.rule.tiling() != Tiling::Square || matches!(self.rule, Rule::Margolus(_))
+    }
+
+    /// Get the closest size the grid can be resized to, rounding tiled and
+    /// Margolus grid sides down to even values
+    pub fn valid_size(&self, (width, height): (usize, usize)) -> (usize, usize) {
+        match self.needs_even_sides() {
+            false => (width.max(1), height.max(1)),
+            true => {
+                let even = |side: usize| (side - side % 2).max(2);
+                (even(width), even(height))
+            }
+        }
+    }
+
+    /// Resize the grid to the closest valid size for the rule, if needed,
+    /// keeping the top left cells in place
+    pub fn fit_size_to_rule(&mut self) {
+        let size = self.valid_size(self.grid_size());
+        if size != self.grid_size() {
+            self.resize(size, Anchor::TopLeft);
+        }
+    }
+
+    /// Resize the grid, keeping cells in place around the anchor
+    pub fn resize(&mut self, size: (usize, usize), anchor: Anchor) {
+        let (width, height) = self.valid_size(size);
+        let (mut dx, mut dy) = anchor.offset((self.width, self.height), (width, height));
+        if self.needs_even_sides() {
+            dx -= dx.rem_euclid(2);
+            dy -= dy.rem_euclid(2);
+        }
+
+        let mut data = vec![false; width * height];
+        let mut previous = vec![false; width * height];
+        let mut species = vec![0; width * height];
+        let mut life = vec![0; width * height];
+        for y in 0..self.height {
+            for x in 0..self.width {
+                let (nx, ny) = (x as isize + dx, y as isize + dy);
+                if nx < 0 || ny < 0 || nx >= width as isize || ny >= height as isize {
+                    continue;
+                }
+
+                let idx = self.pos_to_index((x, y));
+                let new_idx = nx as usize + ny as usize * width;
+                data[new_idx] = self.data[idx];
+                previous[new_idx] = self.previous[idx];
+                species[new_idx] = self.species[idx];
+                life[new_idx] = self.life[idx];
+            }
+        }
+
+        self.edits += 1;
+        self.width = width;
+        self.height = height;
+        self.data = data;
+        self.previous = previous;
+        self.species = species;
+        self.life = life;
+        self.stats = GameStats::from_state(self);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::margolus::CRITTERS;
+    use super::super::tiling::HEX_LIFE;
+    use super::*;
+
+    fn state_with_cell(size: (usize, usize), pos: (usize, usize)) -> GameState {
+        let mut state = GameState::new(size);
+        state.set_cell_at_pos(pos, true, 2);
+        state
+    }
+
+    #[test]
+    fn test_grow() {
+        let cases = [
+            (Anchor::TopLeft, (1, 2)),
+            (Anchor::TopRight, (5, 2)),
+            (Anchor::Center, (3, 4)),
+            (Anchor::BottomLeft, (1, 6)),
+            (Anchor::BottomRight, (5, 6)),
+        ];
+
+        for (anchor, expected) in cases.iter() {
+            let mut state = state_with_cell((4, 4), (1, 2));
+            state.resize((8, 8), *anchor);
+
+            assert_eq!(state.grid_size(), (8, 8));
+            assert_eq!(state.stats.population, 1, "{}", anchor);
+            let idx = state.pos_to_index(*expected);
+            assert!(state.data[idx], "{}", anchor);
+            assert_eq!(state.species[idx], 2);
+        }
+    }
+
+    #[test]
+    fn test_shrink() {
+        let mut state = state_with_cell((8, 8), (3, 4));
+        state.set_value_at_pos((0, 0), true);
+        state.resize((4, 4), Anchor::Center);
+
+        // The corner cell is cropped
+        assert_eq!(state.stats.population, 1);
+        assert!(state.data[state.pos_to_index((1, 2))]);
+
+        let mut state = state_with_cell((8, 8), (6, 6));
+        state.resize((4, 4), Anchor::TopLeft);
+        assert_eq!(state.stats.population, 0);
+        assert_eq!(state.data.len(), 16);
+    }
+
+    #[test]
+    fn test_resize_undo() {
+        let mut state = state_with_cell((4, 4), (1, 2));
+        let snapshot = state.snapshot();
+        state.resize((6, 3), Anchor::BottomRight);
+        assert_eq!(state.grid_size(), (6, 3));
+
+        state.restore(&snapshot);
+        assert_eq!(state.grid_size(), (4, 4));
+        assert!(state.data[state.pos_to_index((1, 2))]);
+    }
+
+    #[test]
+    fn test_tiled_grid_stays_even() {
+        let mut state = GameState::new((8, 8));
+        state.set_rule(Rule::Tiled(HEX_LIFE));
+        state.set_value_at_pos((2, 3), true);
+        assert_eq!(state.valid_size((7, 1)), (6, 2));
+
+        // The center offset of one cell is rounded to keep the row parity
+        state.resize((11, 11), Anchor::Center);
+        assert_eq!(state.grid_size(), (10, 10));
+        assert!(state.data[state.pos_to_index((2, 3))]);
+
+        state.resize((14, 14), Anchor::Center);
+        assert!(state.data[state.pos_to_index((4, 5))]);
+    }
+
+    #[test]
+    fn test_margolus_grid_stays_even() {
+        let mut state = state_with_cell((9, 7), (3, 4));
+        state.set_rule(Rule::Margolus(CRITTERS));
+        assert_eq!(state.grid_size(), (8, 6));
+        assert!(state.data[state.pos_to_index((3, 4))]);
+
+        // Cells stay at the same position in their block
+        state.resize((11, 11), Anchor::Center);
+        assert_eq!(state.grid_size(), (10, 10));
+        assert!(state.data[state.pos_to_index((3, 6))]);
+
+        // Other rules keep odd sizes
+        state.set_rule(Rule::Life);
+        state.resize((11, 11), Anchor::TopLeft);
+        assert_eq!(state.grid_size(), (11, 11));
+    }
+}
@@ -0,0 +1,122 @@
+//! Automaton rules
+
+use super::margolus::MargolusRule;
+use super::second_order::SecondOrderRule;
+use super::species::SpeciesRule;
+use super::tiling::{Tiling, TilingRule};
+use super::volume::VolumeRule;
+
+/// Automaton rule
+#[derive(Clone, Copy, PartialEq, Debug, Default)]
+pub enum Rule {
+    /// Conway's Game of Life
+    #[default]
+    Life,
+    /// Margolus neighborhood block rule
+    Margolus(MargolusRule),
+    /// Second-order reversible rule
+    SecondOrder(SecondOrderRule),
+    /// Multi-species Life variant
+    Species(SpeciesRule),
+    /// Life-like rule on a non-square tiling
+    Tiled(TilingRule),
+    /// 3D Life-like rule
+    Volume(VolumeRule),
+}
+
+impl Rule {
+    /// Get available rules
+    pub fn presets() -> Vec<Self> {
+        let mut rules = vec![Self::Life];
+        rules.extend(MargolusRule::presets().into_iter().map(Self::Margolus));
+        rules.extend(
+            SecondOrderRule::presets()
+                .into_iter()
+                .map(Self::SecondOrder),
+        );
+        rules.extend(SpeciesRule::presets().into_iter().map(Self::Species));
+        rules.extend(TilingRule::presets().into_iter().map(Self::Tiled));
+        rules.extend(VolumeRule::presets().into_iter().map(Self::Volume));
+        rules
+    }
+
+    /// Get rule name
+    pub fn name(&self) -> &str {
+        match self {
+            Self::Life => "Game of Life",
+            Self::Margolus(rule) => rule.name,
+            Self::SecondOrder(rule) => rule.name,
+            Self::Species(rule) => rule.name,
+            Self::Tiled(rule) => rule.name,
+            Self::Volume(rule) => rule.name,
+        }
+    }
+
+    /// Get the rule notation, as used in pattern files
+    pub fn notation(&self) -> String {
+        match self {
+            Self::Life => "B3/S23".to_string(),
+            Self::Volume(rule) => rule.to_string(),
+            rule => rule.name().to_string(),
+        }
+    }
+
+    /// Find a rule from its name or notation, ignoring case
+    pub fn from_name(name: &str) -> Option<Self> {
+        let name = name.trim();
+        if ["life", "conway", "b3/s23"]
+            .iter()
+            .any(|alias| alias.eq_ignore_ascii_case(name))
+        {
+            return Some(Self::Life);
+        }
+
+        Self::presets()
+            .into_iter()
+            .find(|rule| {
+                rule.name().eq_ignore_ascii_case(name) || rule.notation().eq_ignore_ascii_case(name)
+            })
+            .or_else(|| VolumeRule::parse(name).map(Self::Volume))
+    }
+
+    /// Check if the rule can be run backwards
+    pub fn is_reversible(&self) -> bool {
+        match self {
+            Self::Life => false,
+            Self::Margolus(rule) => rule.is_permutation(),
+            Self::SecondOrder(_) => true,
+            Self::Species(_) | Self::Tiled(_) | Self::Volume(_) => false,
+        }
+    }
+
+    /// Get the number of species handled by the rule
+    pub fn species_count(&self) -> u8 {
+        match self {
+            Self::Species(rule) => rule.count,
+            _ => 1,
+        }
+    }
+
+    /// Get the grid tiling used by the rule
+    pub fn tiling(&self) -> Tiling {
+        match self {
+            Self::Tiled(rule) => rule.tiling,
+            _ => Tiling::Square,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_name() {
+        assert_eq!(Rule::from_name("B3/S23"), Some(Rule::Life));
+        assert_eq!(Rule::from_name(" life "), Some(Rule::Life));
+        for rule in Rule::presets() {
+            assert_eq!(Rule::from_name(&rule.notation()), Some(rule));
+        }
+        assert!(Rule::from_name("unknown").is_none());
+    }
+}
@@ -0,0 +1,149 @@
+//! Simulation scheduling
+//!
+//! Generations are scheduled from elapsed time, so the simulation speed does
+//! not depend on the frame rate.
+
+/// Default speed, in generations per second
+pub const DEFAULT_SPEED: f32 = 60.0;
+/// Minimum simulation speed, in generations per second
+pub const MIN_SPEED: f32 = 0.1;
+/// Maximum simulation speed, in generations per second
+pub const MAX_SPEED: f32 = 1000.0;
+
+/// Default maximum number of generations run in one update, so long steps
+/// are spread across frames
+pub const DEFAULT_TICK_BUDGET: usize = 100;
+
+/// Maximum time to catch up in one update, in seconds
+const MAX_CATCH_UP: f32 = 0.25;
+
+/// Simulation scheduler
+#[derive(Clone)]
+pub struct Scheduler {
+    /// Speed, in generations per second
+    pub speed: f32,
+    /// Generations to run once, even when paused
+    pub pending_ticks: usize,
+    /// Generation to run to, even when paused
+    pub target_tick: Option<usize>,
+    /// Maximum number of generations run in one update
+    pub tick_budget: usize,
+    accumulator: f32,
+}
+
+impl Scheduler {
+    pub fn new() -> Self {
+        Self {
+            speed: DEFAULT_SPEED,
+            pending_ticks: 0,
+            target_tick: None,
+            tick_budget: DEFAULT_TICK_BUDGET,
+            accumulator: 0.0,
+        }
+    }
+
+    /// Queue generations to run
+    pub fn step(&mut self, count: usize) {
+        self.pending_ticks += count;
+    }
+
+    /// Run at the current speed until a generation is reached
+    pub fn run_until(&mut self, tick: usize) {
+        self.target_tick = Some(tick);
+    }
+
+    /// Stop queued generations and targets
+    pub fn cancel(&mut self) {
+        self.pending_ticks = 0;
+        self.target_tick = None;
+    }
+
+    /// Get the number of generations to run for an update of `dt` seconds,
+    /// within the tick budget. Queued generations left over run in the next
+    /// updates.
+    pub fn scheduled_ticks(&mut self, dt: f32, running: bool, current_tick: usize) -> usize {
+        let budget = self.tick_budget.max(1);
+        let pending = self.pending_ticks.min(budget);
+        self.pending_ticks -= pending;
+
+        let target_remaining = match self.target_tick {
+            Some(target) if target > current_tick => Some(target - current_tick),
+            Some(_) => {
+                self.target_tick = None;
+                None
+            }
+            None => None,
+        };
+
+        if !running && target_remaining.is_none() {
+            self.accumulator = 0.0;
+            return pending;
+        }
+
+        let max_accumulator = (self.speed * MAX_CATCH_UP).max(1.0);
+        self.accumulator = (self.accumulator + dt * self.speed).min(max_accumulator);
+        let mut ticks = self.accumulator.floor().max(0.0) as usize;
+        self.accumulator -= ticks as f32;
+
+        if let Some(remaining) = target_remaining {
+            ticks = ticks.min(remaining);
+        }
+
+        (pending + ticks).min(budget)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_speed() {
+        let mut scheduler = Scheduler::new();
+        scheduler.speed = 120.0;
+        assert_eq!(scheduler.scheduled_ticks(0.1, true, 0), 12);
+
+        scheduler.speed = 0.5;
+        let ticks = (0..8)
+            .map(|_| scheduler.scheduled_ticks(0.5, true, 0))
+            .sum::<usize>();
+        assert_eq!(ticks, 2);
+    }
+
+    #[test]
+    fn test_paused() {
+        let mut scheduler = Scheduler::new();
+        assert_eq!(scheduler.scheduled_ticks(1.0, false, 0), 0);
+
+        scheduler.step(3);
+        assert_eq!(scheduler.scheduled_ticks(1.0, false, 0), 3);
+        assert_eq!(scheduler.scheduled_ticks(1.0, false, 3), 0);
+    }
+
+    #[test]
+    fn test_run_until() {
+        let mut scheduler = Scheduler::new();
+        scheduler.speed = 100.0;
+        scheduler.run_until(30);
+
+        assert_eq!(scheduler.scheduled_ticks(0.2, false, 0), 20);
+        assert_eq!(scheduler.scheduled_ticks(0.2, false, 20), 10);
+        assert_eq!(scheduler.scheduled_ticks(0.2, false, 30), 0);
+        assert_eq!(scheduler.target_tick, None);
+    }
+
+    #[test]
+    fn test_tick_budget() {
+        let mut scheduler = Scheduler::new();
+        scheduler.tick_budget = 40;
+        scheduler.step(100);
+
+        assert_eq!(scheduler.scheduled_ticks(0.1, false, 0), 40);
+        assert_eq!(scheduler.scheduled_ticks(0.1, false, 40), 40);
+        assert_eq!(scheduler.scheduled_ticks(0.1, false, 80), 20);
+        assert_eq!(scheduler.scheduled_ticks(0.1, false, 100), 0);
+
+        scheduler.speed = MAX_SPEED;
+        assert_eq!(scheduler.scheduled_ticks(1.0, true, 100), 40);
+    }
+}
@@ -0,0 +1,370 @@
+//! Soup search
+//!
+//! Random soups are placed at the center of a larger empty area, run until
+//! they stabilize, then censused. Each soup uses its own seed, derived from
+//! the search seed and the soup index, so any soup can be reproduced.
+//!
+//! The area wraps around, so gliders escaping the soup are removed when they
+//! reach the area edges, before they crash back into the debris. They are
+//! still counted in the census.
+
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::thread;
+
+use super::apgcode::{apgcode, common_name};
+use super::census::UNIDENTIFIED;
+use super::{Census, GameState, Pattern};
+
+/// Maximum number of soup seeds kept for each object
+const MAX_SEEDS_PER_OBJECT: usize = 10;
+
+/// Glider apgcode
+const GLIDER: &str = "xq4_153";
+
+/// Width of the band along the area edges where escaping gliders are removed
+const EDGE_MARGIN: usize = 8;
+
+/// Generations between two escaping glider checks, a glider moves by at most
+/// a quarter of this distance so it cannot cross the edge band unnoticed
+const GLIDER_CHECK_INTERVAL: usize = 16;
+
+/// Soup search configuration
+#[derive(Clone, Debug)]
+pub struct SearchConfig {
+    /// Base seed, the soup `n` uses the seed `seed + n`
+    pub seed: u64,
+    /// Soup side, in cells
+    pub soup_size: usize,
+    /// Area side, in cells
+    pub area_size: usize,
+    /// Generations after which a soup is censused even if not stable
+    pub max_generations: usize,
+    /// Worker threads
+    pub threads: usize,
+}
+
+impl Default for SearchConfig {
+    fn default() -> Self {
+        Self {
+            seed: 0,
+            soup_size: 16,
+            area_size: 64,
+            max_generations: 4000,
+            threads: thread::available_parallelism().map_or(1, |n| n.get()),
+        }
+    }
+}
+
+/// Result of a single soup
+#[derive(Clone, Debug)]
+pub struct SoupResult {
+    pub seed: u64,
+    pub census: Census,
+    /// Set when the soup became periodic before the generation limit
+    pub stabilized: bool,
+}
+
+/// Aggregated search results
+#[derive(Clone, Debug)]
+pub struct Search {
+    pub config: SearchConfig,
+    /// Number of soups run so far
+    pub soups: u64,
+    /// Seeds of soups which did not stabilize
+    pub unstabilized: Vec<u64>,
+    /// Object counts, by apgcode
+    pub objects: BTreeMap<String, usize>,
+    /// First soup seeds producing each object
+    pub seeds: BTreeMap<String, Vec<u64>>,
+}
+
+impl SearchConfig {
+    /// Get the seed of a soup from its index
+    pub fn soup_seed(&self, index: u64) -> u64 {
+        self.seed.wrapping_add(index)
+    }
+
+    /// Run a single soup
+    pub fn run_soup(&self, seed: u64) -> SoupResult {
+        let mut state = GameState::new((self.area_size, self.area_size));
+        let offset = (self.area_size - self.soup_size) / 2;
+        state.soup.region = Some((offset, offset, self.soup_size, self.soup_size));
+        state.randomize_with_seed(seed);
+        self.settle(seed, state)
+    }
+
+    /// Run a soup until it stabilizes, then census it
+    fn settle(&self, seed: u64, mut state: GameState) -> SoupResult {
+        state.detect_cycles = true;
+
+        let mut stabilized = false;
+        let mut escaped = 0;
+        while state.current_tick < self.max_generations {
+            state.tick();
+            if state.current_tick.is_multiple_of(GLIDER_CHECK_INTERVAL) {
+                escaped += remove_escaping_gliders(&mut state);
+            }
+            if state.cycle_detector.result.is_some() {
+                stabilized = true;
+                break;
+            }
+        }
+
+        let mut census = Census::from_state(&state);
+        if escaped > 0 {
+            *census.objects.entry(GLIDER.to_string()).or_insert(0) += escaped;
+        }
+
+        SoupResult {
+            seed,
+            census,
+            stabilized,
+        }
+    }
+}
+
+/// Remove isolated gliders touching the area edge band, returns their count
+fn remove_escaping_gliders(state: &mut GameState) -> usize {
+    let (width, height) = state.grid_size();
+    let wrap = |(x, y): (i32, i32)| {
+        (
+            x.rem_euclid(width as i32) as usize,
+            y.rem_euclid(height as i32) as usize,
+        )
+    };
+    let in_band = |(x, y): (usize, usize)| {
+        x < EDGE_MARGIN || y < EDGE_MARGIN || x + EDGE_MARGIN >= width || y + EDGE_MARGIN >= height
+    };
+
+    let gliders = Pattern::components(state)
+        .into_iter()
+        .filter(|component| component.population() == 5)
+        .filter(|component| component.cells.iter().any(|cell| in_band(wrap(*cell))))
+        .filter(|component| apgcode(component).as_deref() == Some(GLIDER))
+        .collect::<Vec<_>>();
+
+    for glider in &gliders {
+        for cell in &glider.cells {
+            state.set_value_at_pos(wrap(*cell), false);
+        }
+    }
+
+    gliders.len()
+}
+
+impl Search {
+    pub fn new(config: SearchConfig) -> Self {
+        Self {
+            config,
+            soups: 0,
+            unstabilized: vec![],
+            objects: BTreeMap::new(),
+            seeds: BTreeMap::new(),
+        }
+    }
+
+    /// Run the next `count` soups, spread over the worker threads
+    pub fn run_batch(&mut self, count: u64) {
+        let threads = self.config.threads.max(1) as u64;
+        let first = self.soups;
+        let config = &self.config;
+
+        let mut results = thread::scope(|scope| {
+            let workers = (0..threads)
+                .map(|worker| {
+                    scope.spawn(move || {
+                        (worker..count)
+                            .step_by(threads as usize)
+                            .map(|index| config.run_soup(config.soup_seed(first + index)))
+                            .collect::<Vec<_>>()
+                    })
+                })
+                .collect::<Vec<_>>();
+
+            workers
+                .into_iter()
+                .flat_map(|worker| worker.join().expect("soup worker panicked"))
+                .collect::<Vec<_>>()
+        });
+
+        // Keep results in soup order, so reports do not depend on threads
+        results.sort_by_key(|result| result.seed.wrapping_sub(config.seed));
+        for result in results {
+            self.add(result);
+        }
+    }
+
+    fn add(&mut self, result: SoupResult) {
+        self.soups += 1;
+        if !result.stabilized {
+            self.unstabilized.push(result.seed);
+        }
+
+        for (code, count) in result.census.objects {
+            let seeds = self.seeds.entry(code.clone()).or_default();
+            if seeds.len() < MAX_SEEDS_PER_OBJECT {
+                seeds.push(result.seed);
+            }
+            *self.objects.entry(code).or_insert(0) += count;
+        }
+    }
+
+    /// Get the total object count
+    pub fn total(&self) -> usize {
+        self.objects.values().sum()
+    }
+
+    /// Get objects sorted by decreasing count
+    pub fn sorted(&self) -> Vec<(&str, usize)> {
+        let mut objects = self
+            .objects
+            .iter()
+            .map(|(code, count)| (code.as_str(), *count))
+            .collect::<Vec<_>>();
+        objects.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(b.0)));
+        objects
+    }
+
+    /// Get identified objects seen at most `max_count` times, with their seeds
+    pub fn rare_objects(&self, max_count: usize) -> Vec<(&str, usize, &[u64])> {
+        self.sorted()
+            .into_iter()
+            .rev()
+            .filter(|(code, count)| *count <= max_count && *code != UNIDENTIFIED)
+            .map(|(code, count)| (code, count, self.seeds[code].as_slice()))
+            .collect()
+    }
+
+    /// Get a text report of the search
+    pub fn report(&self, rare_threshold: usize) -> String {
+        let mut output = String::new();
+        let config = &self.config;
+        let name = |code| common_name(code).unwrap_or("-");
+
+        writeln!(output, "caw soup search").unwrap();
+        writeln!(output).unwrap();
+        writeln!(output, "Seed: {}", config.seed).unwrap();
+        writeln!(
+            output,
+            "Soups: {} ({} not stabilized after {} generations)",
+            self.soups,
+            self.unstabilized.len(),
+            config.max_generations
+        )
+        .unwrap();
+        writeln!(
+            output,
+            "Soup size: {0}x{0}, in a {1}x{1} area",
+            config.soup_size, config.area_size
+        )
+        .unwrap();
+        writeln!(output, "Objects: {}", self.total()).unwrap();
+
+        writeln!(output).unwrap();
+        writeln!(output, "Counts:").unwrap();
+        for (code, count) in self.sorted() {
+            writeln!(output, "{:>12} {:<24} {}", count, name(code), code).unwrap();
+        }
+
+        writeln!(output).unwrap();
+        writeln!(
+            output,
+            "Rare objects (seen at most {} times), with soup seeds:",
+            rare_threshold
+        )
+        .unwrap();
+        for (code, count, seeds) in self.rare_objects(rare_threshold) {
+            let seeds = seeds.iter().map(u64::to_string).collect::<Vec<_>>();
+            writeln!(
+                output,
+                "{:>12} {:<24} {} [{}]",
+                count,
+                name(code),
+                code,
+                seeds.join(", ")
+            )
+            .unwrap();
+        }
+
+        output
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(threads: usize) -> SearchConfig {
+        SearchConfig {
+            seed: 1234,
+            soup_size: 8,
+            area_size: 32,
+            max_generations: 1000,
+            threads,
+        }
+    }
+
+    #[test]
+    fn test_soup_is_reproducible() {
+        let config = config(1);
+        let first = config.run_soup(42);
+        let second = config.run_soup(42);
+        assert_eq!(first.census.objects, second.census.objects);
+        assert_eq!(first.census.generation, second.census.generation);
+    }
+
+    #[test]
+    fn test_search_does_not_depend_on_threads() {
+        let mut single = Search::new(config(1));
+        single.run_batch(6);
+        single.run_batch(2);
+
+        let mut multi = Search::new(config(3));
+        multi.run_batch(8);
+
+        assert_eq!(single.soups, 8);
+        assert_eq!(single.objects, multi.objects);
+        assert_eq!(single.seeds, multi.seeds);
+        assert_eq!(single.unstabilized, multi.unstabilized);
+        assert_eq!(single.report(10), multi.report(10));
+    }
+
+    #[test]
+    fn test_soup_settling_to_beacon() {
+        let mut state = GameState::new((32, 32));
+        for (x, y) in [(0, 1), (1, 0), (1, 1), (2, 2), (2, 3), (3, 2)] {
+            state.set_value_at_pos((x + 14, y + 14), true);
+        }
+
+        let result = config(1).settle(0, state);
+        assert!(result.stabilized);
+        assert_eq!(result.census.objects.get("xp2_318c"), Some(&1));
+        assert_eq!(result.census.total(), 1);
+    }
+
+    #[test]
+    fn test_escaping_gliders_are_removed() {
+        let mut state = GameState::new((32, 32));
+        for pos in [(15, 15), (16, 15), (15, 16), (16, 16)] {
+            state.set_value_at_pos(pos, true);
+        }
+        // Glider moving towards the bottom right corner
+        for pos in [(21, 20), (22, 21), (20, 22), (21, 22), (22, 22)] {
+            state.set_value_at_pos(pos, true);
+        }
+
+        assert_eq!(remove_escaping_gliders(&mut state), 0);
+
+        let mut escaped = 0;
+        for _ in 0..64 {
+            state.tick();
+            escaped += remove_escaping_gliders(&mut state);
+        }
+
+        assert_eq!(escaped, 1);
+        let census = Census::from_state(&state);
+        assert_eq!(census.objects.get("xs4_33"), Some(&1));
+        assert_eq!(census.total(), 1);
+    }
+}
@@ -0,0 +1,42 @@
+//! Second-order reversible rules
+//!
+//! The next generation is computed from a Life-like rule applied on the
+//! current generation, then XOR-ed with the previous generation:
+//! `next = f(current) ^ previous`. As `previous = f(current) ^ next`, the
+//! rule can be run backwards exactly.
+
+/// Second-order rule, built upon a Life-like `f` function
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct SecondOrderRule {
+    pub name: &'static str,
+    pub birth: &'static [usize],
+    pub survival: &'static [usize],
+}
+
+/// Fredkin parity rule: a cell is set if it has an odd number of neighbors
+pub const FREDKIN: SecondOrderRule = SecondOrderRule {
+    name: "Fredkin parity (second-order)",
+    birth: &[1, 3, 5, 7],
+    survival: &[1, 3, 5, 7],
+};
+
+/// Game of Life as a second-order rule
+pub const LIFE: SecondOrderRule = SecondOrderRule {
+    name: "Game of Life (second-order)",
+    birth: &[3],
+    survival: &[2, 3],
+};
+
+/// HighLife as a second-order rule
+pub const HIGHLIFE: SecondOrderRule = SecondOrderRule {
+    name: "HighLife (second-order)",
+    birth: &[3, 6],
+    survival: &[2, 3],
+};
+
+impl SecondOrderRule {
+    /// Get preset rules
+    pub fn presets() -> Vec<Self> {
+        vec![FREDKIN, LIFE, HIGHLIFE]
+    }
+}
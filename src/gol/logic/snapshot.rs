@@ -0,0 +1,63 @@
+//! Game state snapshots
+
+use super::{GameState, GameStats, Rule, Volume};
+
+/// Copy of the cells and rule of a game state
+#[derive(Clone)]
+pub struct Snapshot {
+    pub rule: Rule,
+    pub width: usize,
+    pub height: usize,
+    pub data: Vec<bool>,
+    pub previous: Vec<bool>,
+    pub species: Vec<u8>,
+    pub life: Vec<u8>,
+    pub current_tick: usize,
+    /// 3D grid, only kept when the rule is a 3D rule
+    pub volume: Option<Volume>,
+}
+
+impl Snapshot {
+    /// Approximate memory used by the snapshot, in bytes
+    pub fn memory_size(&self) -> usize {
+        self.data.len()
+            + self.previous.len()
+            + self.species.len()
+            + self.life.len()
+            + self.volume.as_ref().map(|v| v.cells.len()).unwrap_or(0)
+    }
+}
+
+impl GameState {
+    /// Take a snapshot of the cells and rule
+    pub fn snapshot(&self) -> Snapshot {
+        Snapshot {
+            rule: self.rule,
+            width: self.width,
+            height: self.height,
+            data: self.data.clone(),
+            previous: self.previous.clone(),
+            species: self.species.clone(),
+            life: self.life.clone(),
+            current_tick: self.current_tick,
+            volume: self.active_volume().cloned(),
+        }
+    }
+
+    /// Restore cells and rule from a snapshot, an inactive 3D grid is kept
+    pub fn restore(&mut self, snapshot: &Snapshot) {
+        self.edits += 1;
+        self.rule = snapshot.rule;
+        self.width = snapshot.width;
+        self.height = snapshot.height;
+        self.data = snapshot.data.clone();
+        self.previous = snapshot.previous.clone();
+        self.species = snapshot.species.clone();
+        self.life = snapshot.life.clone();
+        self.current_tick = snapshot.current_tick;
+        if snapshot.volume.is_some() {
+            self.volume = snapshot.volume.clone();
+        }
+        self.stats = GameStats::from_state(self);
+    }
+}
@@ -0,0 +1,248 @@
+//! Random soups
+//!
+//! Each soup is generated from a 64-bit seed, so it can be reproduced
+//! exactly. Soups can be restricted to a region of the grid, and made
+//! symmetric:
+//!
+//! - `C2`: 180° rotation,
+//! - `C4`: 90° rotation,
+//! - `D4`: horizontal and vertical reflections,
+//! - `D8`: all rotations and reflections.
+//!
+//! `C4` and `D8` soups are square, using the largest square centered in the
+//! region.
+
+use std::fmt;
+
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+use super::GameState;
+
+/// Default proportion of live cells
+pub const DEFAULT_DENSITY: f32 = 0.5;
+
+/// Soup symmetry
+#[derive(Clone, Copy, PartialEq, Debug, Default)]
+pub enum Symmetry {
+    #[default]
+    None,
+    C2,
+    C4,
+    D4,
+    D8,
+}
+
+/// Grid region, as `(x, y, width, height)`
+pub type Region = (usize, usize, usize, usize);
+
+/// Soup settings
+#[derive(Clone, Debug)]
+pub struct SoupSettings {
+    /// Seed of the last soup
+    pub seed: u64,
+    /// Proportion of live cells, from 0 to 1
+    pub density: f32,
+    pub symmetry: Symmetry,
+    /// Region to fill, the whole grid if unset
+    pub region: Option<Region>,
+}
+
+impl Default for SoupSettings {
+    fn default() -> Self {
+        Self {
+            seed: 0,
+            density: DEFAULT_DENSITY,
+            symmetry: Symmetry::None,
+            region: None,
+        }
+    }
+}
+
+impl Symmetry {
+    pub fn all() -> [Self; 5] {
+        [Self::None, Self::C2, Self::C4, Self::D4, Self::D8]
+    }
+
+    fn is_square(self) -> bool {
+        matches!(self, Self::C4 | Self::D8)
+    }
+
+    /// Get the images of a cell in a `width` x `height` area
+    fn images(
+        self,
+        (x, y): (usize, usize),
+        (width, height): (usize, usize),
+    ) -> Vec<(usize, usize)> {
+        let (mx, my) = (width - 1 - x, height - 1 - y);
+        match self {
+            Self::None => vec![(x, y)],
+            Self::C2 => vec![(x, y), (mx, my)],
+            Self::C4 => vec![(x, y), (my, x), (mx, my), (y, mx)],
+            Self::D4 => vec![(x, y), (mx, y), (x, my), (mx, my)],
+            Self::D8 => vec![
+                (x, y),
+                (my, x),
+                (mx, my),
+                (y, mx),
+                (mx, y),
+                (x, my),
+                (y, x),
+                (my, mx),
+            ],
+        }
+    }
+}
+
+impl fmt::Display for Symmetry {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::None => write!(f, "None"),
+            Self::C2 => write!(f, "C2"),
+            Self::C4 => write!(f, "C4"),
+            Self::D4 => write!(f, "D4"),
+            Self::D8 => write!(f, "D8"),
+        }
+    }
+}
+
+impl GameState {
+    /// Fill the grid with a new random soup
+    pub fn randomize(&mut self) {
+        let seed = self.rng.gen();
+        self.randomize_with_seed(seed);
+    }
+
+    /// Fill the grid with the soup of a given seed, using the soup settings
+    pub fn randomize_with_seed(&mut self, seed: u64) {
+        self.soup.seed = seed;
+        self.edits += 1;
+        let mut rng = StdRng::seed_from_u64(seed);
+
+        if let Some(volume) = self.active_volume_mut() {
+            return volume.randomize(&mut rng);
+        }
+
+        let (x0, y0, width, height) = self.soup_area();
+        let symmetry = self.soup.symmetry;
+        let density = f64::from(self.soup.density.clamp(0.0, 1.0));
+        let species_count = self.rule.species_count();
+        let mut filled = vec![false; width * height];
+
+        for y in 0..height {
+            for x in 0..width {
+                if filled[x + y * width] {
+                    continue;
+                }
+
+                let alive = rng.gen_bool(density);
+                let species = rng.gen_range(0, species_count);
+                for (ix, iy) in symmetry.images((x, y), (width, height)) {
+                    filled[ix + iy * width] = true;
+                    let idx = self.pos_to_index(((x0 + ix) % self.width, (y0 + iy) % self.height));
+                    self.data[idx] = alive;
+                    self.previous[idx] = false;
+                    self.species[idx] = species;
+                    self.life[idx] = 0;
+                }
+            }
+        }
+    }
+
+    /// Get the area filled by soups, as `(x, y, width, height)`
+    pub fn soup_area(&self) -> Region {
+        let (x, y, width, height) = match self.soup.region {
+            Some((x, y, width, height)) => (
+                x.min(self.width),
+                y.min(self.height),
+                width.min(self.width),
+                height.min(self.height),
+            ),
+            None => (0, 0, self.width, self.height),
+        };
+
+        if self.soup.symmetry.is_square() {
+            let side = width.min(height);
+            (x + (width - side) / 2, y + (height - side) / 2, side, side)
+        } else {
+            (x, y, width, height)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_seed_is_reproducible() {
+        let mut first = GameState::new((32, 32));
+        first.randomize();
+        let seed = first.soup.seed;
+
+        let mut second = GameState::new((32, 32));
+        second.randomize();
+        second.randomize_with_seed(seed);
+        assert_eq!(first.data, second.data);
+    }
+
+    #[test]
+    fn test_density() {
+        let mut state = GameState::new((32, 32));
+        state.soup.density = 0.0;
+        state.randomize();
+        assert!(state.data.iter().all(|alive| !alive));
+
+        state.soup.density = 1.0;
+        state.randomize();
+        assert!(state.data.iter().all(|alive| *alive));
+
+        state.soup.density = 0.25;
+        state.randomize();
+        let population = state.data.iter().filter(|alive| **alive).count();
+        assert!(population > 150 && population < 360);
+    }
+
+    #[test]
+    fn test_region() {
+        let mut state = GameState::new((32, 32));
+        state.soup.density = 1.0;
+        state.soup.region = Some((30, 4, 4, 2));
+        state.randomize();
+
+        let population = state.data.iter().filter(|alive| **alive).count();
+        assert_eq!(population, 8);
+        assert!(state.data[state.pos_to_index((31, 5))]);
+        // Regions wrap around the grid edges
+        assert!(state.data[state.pos_to_index((1, 4))]);
+    }
+
+    #[test]
+    fn test_symmetries() {
+        for symmetry in Symmetry::all().iter() {
+            let mut state = GameState::new((20, 16));
+            state.soup.symmetry = *symmetry;
+            state.soup.region = Some((2, 1, 12, 12));
+            state.randomize();
+
+            let (x0, y0, width, height) = state.soup_area();
+            assert_eq!((width, height), (12, 12));
+            for y in 0..height {
+                for x in 0..width {
+                    let alive = state.data[state.pos_to_index((x0 + x, y0 + y))];
+                    for (ix, iy) in symmetry.images((x, y), (width, height)) {
+                        let image = state.data[state.pos_to_index((x0 + ix, y0 + iy))];
+                        assert_eq!(alive, image, "{} symmetry is broken", symmetry);
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_square_symmetries() {
+        let mut state = GameState::new((20, 16));
+        state.soup.symmetry = Symmetry::C4;
+        assert_eq!(state.soup_area(), (2, 0, 16, 16));
+    }
+}
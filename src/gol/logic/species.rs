@@ -0,0 +1,84 @@
+//! Multi-species Life variants
+//!
+//! Cells follow the Game of Life rule, but each live cell also belongs to a
+//! species. Surviving cells keep their species, and newborn cells take the
+//! majority species of their three parents.
+
+/// Multi-species rule
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct SpeciesRule {
+    pub name: &'static str,
+    pub count: u8,
+}
+
+/// Immigration: two species
+pub const IMMIGRATION: SpeciesRule = SpeciesRule {
+    name: "Immigration",
+    count: 2,
+};
+
+/// QuadLife: four species
+pub const QUADLIFE: SpeciesRule = SpeciesRule {
+    name: "QuadLife",
+    count: 4,
+};
+
+impl SpeciesRule {
+    /// Get preset rules
+    pub fn presets() -> Vec<Self> {
+        vec![IMMIGRATION, QUADLIFE]
+    }
+
+    /// Get the species of a newborn cell from its parents species.
+    ///
+    /// The majority species wins. When all parents are different (only
+    /// possible with more than two species), the missing species is chosen.
+    /// Parents of unknown species are ignored.
+    pub fn birth_species(&self, parents: &[u8]) -> u8 {
+        let parents = parents
+            .iter()
+            .copied()
+            .filter(|species| *species < self.count)
+            .collect::<Vec<_>>();
+        let mut counts = vec![0; self.count as usize];
+        for species in &parents {
+            counts[*species as usize] += 1;
+        }
+
+        if let Some(species) = counts.iter().position(|c| *c > 1) {
+            return species as u8;
+        }
+
+        counts
+            .iter()
+            .position(|c| *c == 0)
+            .map(|species| species as u8)
+            .or_else(|| parents.first().copied())
+            .unwrap_or(0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_birth_species_majority() {
+        assert_eq!(IMMIGRATION.birth_species(&[0, 1, 1]), 1);
+        assert_eq!(IMMIGRATION.birth_species(&[0, 0, 1]), 0);
+        assert_eq!(QUADLIFE.birth_species(&[3, 2, 3]), 3);
+    }
+
+    #[test]
+    fn test_birth_species_missing() {
+        assert_eq!(QUADLIFE.birth_species(&[0, 1, 2]), 3);
+        assert_eq!(QUADLIFE.birth_species(&[3, 1, 0]), 2);
+    }
+
+    #[test]
+    fn test_birth_species_unknown() {
+        assert_eq!(IMMIGRATION.birth_species(&[3, 1, 1]), 1);
+        assert_eq!(IMMIGRATION.birth_species(&[2, 0, 1]), 0);
+        assert_eq!(IMMIGRATION.birth_species(&[2, 3, 3]), 0);
+    }
+}
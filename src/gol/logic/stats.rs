@@ -0,0 +1,246 @@
+//! Per-generation statistics
+//!
+//! Stats are accumulated cell by cell while a generation is committed, so
+//! the simulation does not need a second pass over the grid.
+
+use super::{GameState, Periodicity, MAX_LIFE};
+
+/// Default block sizes used for spatial entropy
+pub const DEFAULT_ENTROPY_BLOCK_SIZES: [usize; 3] = [2, 4, 8];
+
+/// Spatial entropy at one block size
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct BlockEntropy {
+    pub block_size: usize,
+    /// Shannon entropy of the block populations, in bits
+    pub entropy: f32,
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub struct GameStats {
+    pub population: usize,
+    /// Cells born during the last generation
+    pub births: usize,
+    /// Cells which died during the last generation
+    pub deaths: usize,
+    pub moving: usize,
+    pub stopped: usize,
+    pub species: Vec<usize>,
+    /// Bounding box of live cells, as `(min_x, min_y, max_x, max_y)`
+    pub bounding_box: Option<(usize, usize, usize, usize)>,
+    /// Proportion of live cells
+    pub density: f32,
+    pub center_of_mass: Option<(f32, f32)>,
+    pub entropy: Vec<BlockEntropy>,
+    pub periodicity: Option<Periodicity>,
+}
+
+/// Block population counts for one block size
+struct BlockCounts {
+    block_size: usize,
+    columns: usize,
+    counts: Vec<u32>,
+}
+
+/// Stats accumulator
+pub(super) struct StatsBuilder {
+    stats: GameStats,
+    width: usize,
+    size: usize,
+    sum: (usize, usize),
+    blocks: Vec<BlockCounts>,
+}
+
+impl GameStats {
+    pub fn new() -> Self {
+        Self {
+            population: 0,
+            births: 0,
+            deaths: 0,
+            moving: 0,
+            stopped: 0,
+            species: vec![],
+            bounding_box: None,
+            density: 0.0,
+            center_of_mass: None,
+            entropy: vec![],
+            periodicity: None,
+        }
+    }
+
+    /// Compute stats from scratch, after the grid was edited
+    pub fn from_state(game_state: &GameState) -> Self {
+        let mut builder = StatsBuilder::new(game_state);
+
+        if game_state.active_volume().is_none() {
+            for idx in 0..game_state.data.len() {
+                builder.add(
+                    idx,
+                    game_state.previous[idx],
+                    game_state.data[idx],
+                    game_state.life[idx],
+                    game_state.species[idx],
+                );
+            }
+        }
+
+        builder.finish(game_state)
+    }
+}
+
+impl StatsBuilder {
+    pub fn new(game_state: &GameState) -> Self {
+        let (width, height) = (game_state.width, game_state.height);
+        let blocks = game_state
+            .entropy_block_sizes
+            .iter()
+            .filter(|size| **size > 0)
+            .map(|&block_size| {
+                let columns = width.div_ceil(block_size);
+                BlockCounts {
+                    block_size,
+                    columns,
+                    counts: vec![0; columns * height.div_ceil(block_size)],
+                }
+            })
+            .collect();
+
+        Self {
+            stats: GameStats {
+                species: vec![0; game_state.rule.species_count() as usize],
+                ..GameStats::new()
+            },
+            width,
+            size: width * height,
+            sum: (0, 0),
+            blocks,
+        }
+    }
+
+    /// Add a cell, with its previous and new states
+    #[inline]
+    pub fn add(&mut self, idx: usize, was_alive: bool, alive: bool, life: u8, species: u8) {
+        let stats = &mut self.stats;
+        match (was_alive, alive) {
+            (false, true) => stats.births += 1,
+            (true, false) => stats.deaths += 1,
+            _ => (),
+        }
+
+        if !alive {
+            return;
+        }
+
+        if life == MAX_LIFE {
+            stats.stopped += 1;
+        } else {
+            stats.moving += 1;
+        }
+        if let Some(count) = stats.species.get_mut(species as usize) {
+            *count += 1;
+        }
+
+        let (x, y) = (idx % self.width, idx / self.width);
+        stats.bounding_box = Some(match stats.bounding_box {
+            Some((min_x, min_y, max_x, max_y)) => {
+                (min_x.min(x), min_y.min(y), max_x.max(x), max_y.max(y))
+            }
+            None => (x, y, x, y),
+        });
+        self.sum = (self.sum.0 + x, self.sum.1 + y);
+
+        for blocks in &mut self.blocks {
+            let block = x / blocks.block_size + y / blocks.block_size * blocks.columns;
+            blocks.counts[block] += 1;
+        }
+    }
+
+    pub fn finish(self, game_state: &GameState) -> GameStats {
+        let mut stats = self.stats;
+        stats.periodicity = game_state.cycle_detector.result;
+
+        if let Some(volume) = game_state.active_volume() {
+            stats.population = volume.population();
+            stats.density = stats.population as f32 / volume.size().max(1) as f32;
+            return stats;
+        }
+
+        stats.population = stats.moving + stats.stopped;
+        stats.density = stats.population as f32 / self.size.max(1) as f32;
+        if stats.population > 0 {
+            stats.center_of_mass = Some((
+                self.sum.0 as f32 / stats.population as f32,
+                self.sum.1 as f32 / stats.population as f32,
+            ));
+        }
+
+        stats.entropy = self
+            .blocks
+            .iter()
+            .map(|blocks| BlockEntropy {
+                block_size: blocks.block_size,
+                entropy: entropy(&blocks.counts, blocks.block_size * blocks.block_size),
+            })
+            .collect();
+
+        stats
+    }
+}
+
+/// Get the Shannon entropy of the distribution of block populations
+fn entropy(counts: &[u32], max_population: usize) -> f32 {
+    let mut histogram = vec![0usize; max_population + 1];
+    for count in counts {
+        histogram[*count as usize] += 1;
+    }
+
+    let total = counts.len() as f32;
+    histogram
+        .iter()
+        .filter(|n| **n > 0)
+        .map(|n| {
+            let p = *n as f32 / total;
+            -p * p.log2()
+        })
+        .sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_block_stats() {
+        let mut state = GameState::new((8, 8));
+        for pos in &[(3, 3), (4, 3), (3, 4), (4, 4)] {
+            state.set_value_at_pos(*pos, true);
+        }
+        state.entropy_block_sizes = vec![2, 4];
+
+        let stats = GameStats::from_state(&state);
+        assert_eq!(stats.population, 4);
+        assert_eq!(stats.births, 4);
+        assert_eq!(stats.bounding_box, Some((3, 3, 4, 4)));
+        assert_eq!(stats.center_of_mass, Some((3.5, 3.5)));
+        assert_eq!(stats.density, 4.0 / 64.0);
+
+        // 4 blocks with one cell out of 16 for 2x2 blocks
+        let p: f32 = 4.0 / 16.0;
+        let expected = -p * p.log2() - (1.0 - p) * (1.0 - p).log2();
+        assert_eq!(stats.entropy[0].block_size, 2);
+        assert!((stats.entropy[0].entropy - expected).abs() < 1e-6);
+        // One cell in each of the four 4x4 blocks
+        assert_eq!(stats.entropy[1].entropy, 0.0);
+    }
+
+    #[test]
+    fn test_tick_matches_full_pass() {
+        let mut state = GameState::new((16, 16));
+        state.randomize();
+
+        for _ in 0..20 {
+            state.cycle(1);
+            assert_eq!(state.stats, GameStats::from_state(&state));
+        }
+    }
+}
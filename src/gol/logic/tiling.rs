@@ -0,0 +1,124 @@
+//! Grid tilings
+//!
+//! Every tiling is stored in the same `width x height` grid, only the
+//! neighborhood (and the rendering) changes:
+//!
+//! - Hexagonal cells use "odd-r" offset rows: odd rows are shifted by half a
+//!   cell to the right.
+//! - Triangular cells alternate between up and down triangles, a cell is
+//!   pointing up when `x + y` is even.
+//!
+//! Both tilings need an even grid size to wrap correctly.
+
+/// Grid tiling
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Tiling {
+    Square,
+    Hexagonal,
+    Triangular,
+}
+
+const SQUARE_OFFSETS: [(isize, isize); 8] = [
+    (-1, 0),
+    (-1, 1),
+    (0, 1),
+    (1, 1),
+    (1, 0),
+    (1, -1),
+    (0, -1),
+    (-1, -1),
+];
+
+const HEX_EVEN_ROW_OFFSETS: [(isize, isize); 6] =
+    [(-1, 0), (1, 0), (-1, -1), (0, -1), (-1, 1), (0, 1)];
+
+const HEX_ODD_ROW_OFFSETS: [(isize, isize); 6] =
+    [(-1, 0), (1, 0), (0, -1), (1, -1), (0, 1), (1, 1)];
+
+const TRIANGLE_UP_OFFSETS: [(isize, isize); 12] = [
+    (-1, -1),
+    (0, -1),
+    (1, -1),
+    (-2, 0),
+    (-1, 0),
+    (1, 0),
+    (2, 0),
+    (-2, 1),
+    (-1, 1),
+    (0, 1),
+    (1, 1),
+    (2, 1),
+];
+
+const TRIANGLE_DOWN_OFFSETS: [(isize, isize); 12] = [
+    (-1, 1),
+    (0, 1),
+    (1, 1),
+    (-2, 0),
+    (-1, 0),
+    (1, 0),
+    (2, 0),
+    (-2, -1),
+    (-1, -1),
+    (0, -1),
+    (1, -1),
+    (2, -1),
+];
+
+impl Tiling {
+    /// Get neighbor offsets for a cell position
+    pub fn neighbor_offsets(self, (x, y): (usize, usize)) -> &'static [(isize, isize)] {
+        match self {
+            Self::Square => &SQUARE_OFFSETS,
+            Self::Hexagonal if y % 2 == 0 => &HEX_EVEN_ROW_OFFSETS,
+            Self::Hexagonal => &HEX_ODD_ROW_OFFSETS,
+            Self::Triangular if Self::is_triangle_up((x, y)) => &TRIANGLE_UP_OFFSETS,
+            Self::Triangular => &TRIANGLE_DOWN_OFFSETS,
+        }
+    }
+
+    /// Check if a triangular cell is pointing up
+    pub fn is_triangle_up((x, y): (usize, usize)) -> bool {
+        (x + y) % 2 == 0
+    }
+}
+
+/// Life-like rule bound to a tiling
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct TilingRule {
+    pub name: &'static str,
+    pub tiling: Tiling,
+    pub birth: &'static [usize],
+    pub survival: &'static [usize],
+}
+
+/// Hexagonal Life, on the 6 cells neighborhood
+pub const HEX_LIFE: TilingRule = TilingRule {
+    name: "Hexagonal Life (B2/S34)",
+    tiling: Tiling::Hexagonal,
+    birth: &[2],
+    survival: &[3, 4],
+};
+
+/// Hexagonal rule with gliders, on the 6 cells neighborhood
+pub const HEX_GLIDERS: TilingRule = TilingRule {
+    name: "Hexagonal (B245/S3)",
+    tiling: Tiling::Hexagonal,
+    birth: &[2, 4, 5],
+    survival: &[3],
+};
+
+/// Triangular Life, on the 12 cells neighborhood
+pub const TRIANGULAR_LIFE: TilingRule = TilingRule {
+    name: "Triangular Life (B456/S345)",
+    tiling: Tiling::Triangular,
+    birth: &[4, 5, 6],
+    survival: &[3, 4, 5],
+};
+
+impl TilingRule {
+    /// Get preset rules
+    pub fn presets() -> Vec<Self> {
+        vec![HEX_LIFE, HEX_GLIDERS, TRIANGULAR_LIFE]
+    }
+}
@@ -0,0 +1,321 @@
+//! Generation timeline
+//!
+//! Keyframe snapshots are kept every `interval` generations. Any past
+//! generation can then be reconstructed by restoring the closest keyframe
+//! and replaying the simulation from it.
+//!
+//! When the memory budget is exceeded, every other keyframe is dropped and
+//! the interval is doubled, so the whole run stays reachable. The replay can
+//! then be long, so it is spread over several calls within a budget.
+
+use std::collections::VecDeque;
+
+use super::{GameState, Rule, Snapshot};
+
+/// Default keyframe interval, in generations
+pub const DEFAULT_INTERVAL: usize = 20;
+
+/// Default timeline memory budget, in bytes
+pub const DEFAULT_MEMORY_BUDGET: usize = 256 * 1024 * 1024;
+
+/// Cells updated by a call to `Timeline::replay`, so long replays are spread
+/// over several frames
+pub const REPLAY_BUDGET: usize = 4_000_000;
+
+/// Generation timeline
+pub struct Timeline {
+    interval: usize,
+    current_interval: usize,
+    keyframes: VecDeque<Snapshot>,
+    memory_budget: usize,
+    memory_used: usize,
+    latest_tick: usize,
+    edits: Option<usize>,
+    rule: Option<Rule>,
+    /// Generation to reach, while a seek is replayed
+    target: Option<usize>,
+}
+
+impl Default for Timeline {
+    fn default() -> Self {
+        Self::new(DEFAULT_INTERVAL, DEFAULT_MEMORY_BUDGET)
+    }
+}
+
+impl Timeline {
+    pub fn new(interval: usize, memory_budget: usize) -> Self {
+        Self {
+            interval: interval.max(1),
+            current_interval: interval.max(1),
+            keyframes: VecDeque::new(),
+            memory_budget,
+            memory_used: 0,
+            latest_tick: 0,
+            edits: None,
+            rule: None,
+            target: None,
+        }
+    }
+
+    /// Forget all keyframes
+    pub fn reset(&mut self) {
+        self.keyframes.clear();
+        self.memory_used = 0;
+        self.current_interval = self.interval;
+        self.edits = None;
+        self.rule = None;
+        self.target = None;
+    }
+
+    pub fn interval(&self) -> usize {
+        self.interval
+    }
+
+    pub fn set_interval(&mut self, interval: usize) {
+        self.interval = interval.max(1);
+        self.current_interval = self.current_interval.max(self.interval);
+    }
+
+    pub fn memory_budget(&self) -> usize {
+        self.memory_budget
+    }
+
+    pub fn set_memory_budget(&mut self, memory_budget: usize) {
+        self.memory_budget = memory_budget;
+        self.enforce_budget();
+    }
+
+    pub fn memory_used(&self) -> usize {
+        self.memory_used
+    }
+
+    pub fn keyframes_count(&self) -> usize {
+        self.keyframes.len()
+    }
+
+    /// Get the first reachable generation
+    pub fn first_tick(&self) -> Option<usize> {
+        self.keyframes.front().map(|k| k.current_tick)
+    }
+
+    /// Get the latest generation reached since the last edit
+    pub fn latest_tick(&self) -> usize {
+        self.latest_tick
+    }
+
+    /// Track the state, to call after each simulation update.
+    ///
+    /// Edits and rule changes discard keyframes after the current generation.
+    pub fn record(&mut self, state: &GameState) {
+        if self.rule != Some(state.rule) {
+            self.reset();
+            self.rule = Some(state.rule);
+        }
+
+        if self.edits != Some(state.edits) {
+            self.truncate(state.current_tick);
+            self.latest_tick = state.current_tick;
+            self.edits = Some(state.edits);
+            self.push(state);
+            return;
+        }
+
+        self.latest_tick = self.latest_tick.max(state.current_tick);
+        let needs_keyframe = match self.keyframes.back() {
+            Some(keyframe) => state.current_tick >= keyframe.current_tick + self.current_interval,
+            None => true,
+        };
+
+        if needs_keyframe {
+            self.push(state);
+        }
+    }
+
+    /// Get the generation being reached, while a seek is replayed
+    pub fn target(&self) -> Option<usize> {
+        self.target
+    }
+
+    /// Start reconstructing a past generation, pausing the simulation.
+    ///
+    /// The closest keyframe is restored, and the generations after it are
+    /// replayed by `replay`.
+    pub fn seek(&mut self, state: &mut GameState, tick: usize) -> bool {
+        let tick = tick.min(self.latest_tick);
+        let keyframe = match self.keyframes.iter().rev().find(|k| k.current_tick <= tick) {
+            Some(keyframe) => keyframe,
+            None => return false,
+        };
+
+        // Avoid replaying from the keyframe when moving forward
+        if self.edits != Some(state.edits)
+            || state.current_tick > tick
+            || state.current_tick < keyframe.current_tick
+        {
+            state.restore(keyframe);
+        }
+
+        state.running = false;
+        self.edits = Some(state.edits);
+        self.target = Some(tick);
+        true
+    }
+
+    /// Replay the seek until about `budget` cells were updated, returns true
+    /// when no seek is left. Edits and running the simulation cancel it.
+    pub fn replay(&mut self, state: &mut GameState, budget: usize) -> bool {
+        let Some(target) = self.target else {
+            return true;
+        };
+        if self.edits != Some(state.edits) || state.running {
+            self.target = None;
+            return true;
+        }
+
+        let cells = (state.width * state.height).max(1);
+        let mut updated = 0;
+        while state.current_tick < target && updated < budget {
+            state.tick();
+            updated += cells;
+        }
+
+        if state.current_tick >= target {
+            self.target = None;
+        }
+        self.target.is_none()
+    }
+
+    fn push(&mut self, state: &GameState) {
+        let snapshot = state.snapshot();
+        self.memory_used += snapshot.memory_size();
+        self.keyframes.push_back(snapshot);
+        self.enforce_budget();
+    }
+
+    fn truncate(&mut self, tick: usize) {
+        while let Some(keyframe) = self.keyframes.back() {
+            if keyframe.current_tick < tick {
+                break;
+            }
+
+            self.memory_used -= keyframe.memory_size();
+            self.keyframes.pop_back();
+        }
+    }
+
+    fn enforce_budget(&mut self) {
+        while self.memory_used > self.memory_budget && self.keyframes.len() > 1 {
+            // Drop every other keyframe
+            let keyframes = std::mem::take(&mut self.keyframes);
+            for (idx, keyframe) in keyframes.into_iter().enumerate() {
+                if idx % 2 == 0 {
+                    self.keyframes.push_back(keyframe);
+                } else {
+                    self.memory_used -= keyframe.memory_size();
+                }
+            }
+
+            self.current_interval *= 2;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn run(state: &mut GameState, timeline: &mut Timeline, ticks: usize) {
+        timeline.record(state);
+        for _ in 0..ticks {
+            state.tick();
+            timeline.record(state);
+        }
+    }
+
+    #[test]
+    fn test_seek() {
+        let mut state = GameState::new((16, 16));
+        state.randomize();
+        let mut reference = state.clone();
+        let mut timeline = Timeline::new(10, DEFAULT_MEMORY_BUDGET);
+
+        run(&mut state, &mut timeline, 100);
+        assert_eq!(timeline.first_tick(), Some(0));
+        assert_eq!(timeline.latest_tick(), 100);
+        assert_eq!(timeline.keyframes_count(), 11);
+
+        for _ in 0..37 {
+            reference.tick();
+        }
+        assert!(timeline.seek(&mut state, 37));
+        assert!(timeline.replay(&mut state, usize::MAX));
+        assert_eq!(state.current_tick, 37);
+        assert_eq!(state.data, reference.data);
+
+        // Seeking is not an edit: running again keeps the keyframes
+        run(&mut state, &mut timeline, 10);
+        assert_eq!(timeline.latest_tick(), 100);
+        assert_eq!(timeline.keyframes_count(), 11);
+    }
+
+    #[test]
+    fn test_edit_truncates() {
+        let mut state = GameState::new((16, 16));
+        let mut timeline = Timeline::new(10, DEFAULT_MEMORY_BUDGET);
+
+        run(&mut state, &mut timeline, 50);
+        timeline.seek(&mut state, 25);
+        timeline.replay(&mut state, usize::MAX);
+        state.set_value_at_pos((1, 1), true);
+        timeline.record(&state);
+
+        assert_eq!(timeline.latest_tick(), 25);
+        assert_eq!(timeline.keyframes_count(), 4);
+    }
+
+    #[test]
+    fn test_memory_budget() {
+        let mut state = GameState::new((16, 16));
+        state.randomize();
+        let keyframe_size = state.snapshot().memory_size();
+        let mut timeline = Timeline::new(1, keyframe_size * 8);
+
+        run(&mut state, &mut timeline, 100);
+        assert!(timeline.memory_used() <= keyframe_size * 8);
+        assert_eq!(timeline.first_tick(), Some(0));
+
+        let mut reference = GameState::new((16, 16));
+        reference.restore(&timeline.keyframes[0]);
+        for _ in 0..90 {
+            reference.tick();
+        }
+        assert!(timeline.seek(&mut state, 90));
+        assert!(timeline.replay(&mut state, usize::MAX));
+        assert_eq!(state.data, reference.data);
+    }
+
+    #[test]
+    fn test_replay_budget() {
+        let mut state = GameState::new((16, 16));
+        state.randomize();
+        let mut timeline = Timeline::new(10, DEFAULT_MEMORY_BUDGET);
+        run(&mut state, &mut timeline, 50);
+
+        // Two generations per call
+        assert!(timeline.seek(&mut state, 35));
+        assert!(!state.running);
+        assert_eq!(state.current_tick, 30);
+        assert!(!timeline.replay(&mut state, 16 * 16 * 2));
+        assert_eq!(state.current_tick, 32);
+        assert!(!timeline.replay(&mut state, 16 * 16 * 2));
+        assert!(timeline.replay(&mut state, 16 * 16 * 2));
+        assert_eq!(state.current_tick, 35);
+        assert_eq!(timeline.target(), None);
+
+        // Edits cancel the seek
+        assert!(timeline.seek(&mut state, 45));
+        state.set_value_at_pos((1, 1), true);
+        assert!(timeline.replay(&mut state, usize::MAX));
+        assert_eq!(state.current_tick, 40);
+    }
+}
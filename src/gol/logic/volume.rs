@@ -0,0 +1,378 @@
+//! 3D cellular automata
+//!
+//! Rules use the "S/B/N/M" notation, as in "4/4/5/M":
+//!
+//! - survival neighbor counts,
+//! - birth neighbor counts,
+//! - number of states,
+//! - neighborhood, `M` for Moore (26 cells) or `N` for von Neumann (6 cells).
+//!
+//! Counts can be lists and ranges, like "2,6,9" or "13-26".
+//! A cell in state 1 is alive, states above 1 are decaying cells, which are
+//! not counted as neighbors and cannot be born again until they reach 0.
+
+use std::fmt;
+
+use rand::Rng;
+
+/// 3D neighborhood
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Neighborhood3d {
+    Moore,
+    VonNeumann,
+}
+
+/// 3D rule
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct VolumeRule {
+    pub name: &'static str,
+    /// Survival counts, as a bit mask
+    pub survival: u32,
+    /// Birth counts, as a bit mask
+    pub birth: u32,
+    pub states: u8,
+    pub neighborhood: Neighborhood3d,
+}
+
+/// Volume view mode
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum VolumeView {
+    /// Show one Z slice
+    Slice,
+    /// Show the most alive cell along Z
+    MaxProjection,
+    /// Show the proportion of alive cells along Z
+    DensityProjection,
+}
+
+/// 3D grid
+#[derive(Clone)]
+pub struct Volume {
+    pub width: usize,
+    pub height: usize,
+    pub depth: usize,
+    pub cells: Vec<u8>,
+    pub view: VolumeView,
+    pub slice: usize,
+}
+
+/// Default volume size
+pub const DEFAULT_VOLUME_SIZE: (usize, usize, usize) = (64, 64, 64);
+
+impl VolumeRule {
+    /// Get preset rules
+    pub fn presets() -> Vec<Self> {
+        vec![
+            Self::parse_named("3D 445", "4/4/5/M").unwrap(),
+            Self::parse_named("3D Amoeba", "9-26/5-7,12-13,15/5/M").unwrap(),
+            Self::parse_named("3D Builder", "2,6,9/4,6,8-9/10/M").unwrap(),
+            Self::parse_named("3D Crystal", "0-6/1,3/2/N").unwrap(),
+        ]
+    }
+
+    /// Parse a rule from the "S/B/N/M" notation
+    pub fn parse(notation: &str) -> Option<Self> {
+        Self::parse_named("3D custom", notation)
+    }
+
+    fn parse_named(name: &'static str, notation: &str) -> Option<Self> {
+        let parts = notation.trim().split('/').collect::<Vec<_>>();
+        if parts.len() != 4 {
+            return None;
+        }
+
+        let states = parts[2].trim().parse::<u8>().ok().filter(|s| *s >= 2)?;
+        let neighborhood = match parts[3].trim() {
+            "M" | "m" => Neighborhood3d::Moore,
+            "N" | "n" | "VN" | "vn" => Neighborhood3d::VonNeumann,
+            _ => return None,
+        };
+
+        Some(Self {
+            name,
+            survival: Self::parse_counts(parts[0])?,
+            birth: Self::parse_counts(parts[1])?,
+            states,
+            neighborhood,
+        })
+    }
+
+    fn parse_counts(counts: &str) -> Option<u32> {
+        let mut mask = 0;
+
+        for item in counts.split(',').map(str::trim).filter(|s| !s.is_empty()) {
+            let mut bounds = item.splitn(2, '-');
+            let start = bounds.next()?.trim().parse::<u32>().ok()?;
+            let end = match bounds.next() {
+                Some(end) => end.trim().parse::<u32>().ok()?,
+                None => start,
+            };
+            if start > end || end > 26 {
+                return None;
+            }
+
+            for count in start..=end {
+                mask |= 1 << count;
+            }
+        }
+
+        Some(mask)
+    }
+
+    fn format_counts(mask: u32) -> String {
+        let mut items = vec![];
+        let mut count = 0;
+
+        while count <= 26 {
+            if mask & (1 << count) == 0 {
+                count += 1;
+                continue;
+            }
+
+            let start = count;
+            while count < 26 && mask & (1 << (count + 1)) != 0 {
+                count += 1;
+            }
+            items.push(if start == count {
+                start.to_string()
+            } else {
+                format!("{}-{}", start, count)
+            });
+            count += 1;
+        }
+
+        items.join(",")
+    }
+
+    fn survives(&self, count: usize) -> bool {
+        self.survival & (1 << count) != 0
+    }
+
+    fn is_born(&self, count: usize) -> bool {
+        self.birth & (1 << count) != 0
+    }
+}
+
+impl fmt::Display for VolumeRule {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{}/{}/{}/{}",
+            Self::format_counts(self.survival),
+            Self::format_counts(self.birth),
+            self.states,
+            match self.neighborhood {
+                Neighborhood3d::Moore => "M",
+                Neighborhood3d::VonNeumann => "N",
+            }
+        )
+    }
+}
+
+impl Volume {
+    pub fn new((width, height, depth): (usize, usize, usize)) -> Self {
+        Self {
+            width,
+            height,
+            depth,
+            cells: vec![0; width * height * depth],
+            view: VolumeView::Slice,
+            slice: depth / 2,
+        }
+    }
+
+    pub fn size(&self) -> usize {
+        self.width * self.height * self.depth
+    }
+
+    pub fn pos_to_index(&self, (x, y, z): (usize, usize, usize)) -> usize {
+        x + y * self.width + z * self.width * self.height
+    }
+
+    pub fn is_alive(&self, pos: (usize, usize, usize)) -> bool {
+        self.cells[self.pos_to_index(pos)] == 1
+    }
+
+    pub fn set_value_at_pos(&mut self, pos: (usize, usize, usize), value: bool) {
+        let idx = self.pos_to_index(pos);
+        self.cells[idx] = if value { 1 } else { 0 };
+    }
+
+    pub fn population(&self) -> usize {
+        self.cells.iter().filter(|c| **c == 1).count()
+    }
+
+    pub fn clear(&mut self) {
+        self.cells = vec![0; self.size()];
+    }
+
+    /// Randomize the central half of the volume
+    pub fn randomize<R: Rng>(&mut self, rng: &mut R) {
+        self.clear();
+
+        for z in self.depth / 4..self.depth * 3 / 4 {
+            for y in self.height / 4..self.height * 3 / 4 {
+                for x in self.width / 4..self.width * 3 / 4 {
+                    let idx = self.pos_to_index((x, y, z));
+                    self.cells[idx] = rng.gen_range(0, 2);
+                }
+            }
+        }
+    }
+
+    fn alive_neighbors_count(&self, (x, y, z): (usize, usize, usize), rule: &VolumeRule) -> usize {
+        let wrap =
+            |v: usize, d: isize, max: usize| (v as isize + d).rem_euclid(max as isize) as usize;
+        let mut count = 0;
+
+        for dz in -1..=1 {
+            for dy in -1..=1 {
+                for dx in -1..=1 {
+                    let distance = dx * dx + dy * dy + dz * dz;
+                    let included = match rule.neighborhood {
+                        Neighborhood3d::Moore => distance > 0,
+                        Neighborhood3d::VonNeumann => distance == 1,
+                    };
+
+                    if included
+                        && self.is_alive((
+                            wrap(x, dx, self.width),
+                            wrap(y, dy, self.height),
+                            wrap(z, dz, self.depth),
+                        ))
+                    {
+                        count += 1;
+                    }
+                }
+            }
+        }
+
+        count
+    }
+
+    pub fn tick(&mut self, rule: &VolumeRule) {
+        let mut new_cells = vec![0; self.size()];
+
+        for z in 0..self.depth {
+            for y in 0..self.height {
+                for x in 0..self.width {
+                    let idx = self.pos_to_index((x, y, z));
+                    let state = self.cells[idx];
+
+                    new_cells[idx] = match state {
+                        0 => {
+                            if rule.is_born(self.alive_neighbors_count((x, y, z), rule)) {
+                                1
+                            } else {
+                                0
+                            }
+                        }
+                        1 => {
+                            if rule.survives(self.alive_neighbors_count((x, y, z), rule)) {
+                                1
+                            } else {
+                                2 % rule.states
+                            }
+                        }
+                        _ => (state + 1) % rule.states,
+                    };
+                }
+            }
+        }
+
+        self.cells = new_cells;
+    }
+
+    /// Get the view intensity of a column, from 0 (empty) to 255 (alive)
+    pub fn view_intensity(&self, (x, y): (usize, usize), rule: &VolumeRule) -> u8 {
+        let intensity = |state: u8| match state {
+            0 => 0,
+            _ => 255 - ((state as usize - 1) * 192 / rule.states as usize) as u8,
+        };
+
+        match self.view {
+            VolumeView::Slice => intensity(self.cells[self.pos_to_index((x, y, self.slice))]),
+            VolumeView::MaxProjection => (0..self.depth)
+                .map(|z| intensity(self.cells[self.pos_to_index((x, y, z))]))
+                .max()
+                .unwrap_or(0),
+            VolumeView::DensityProjection => {
+                let alive = (0..self.depth)
+                    .filter(|z| self.is_alive((x, y, *z)))
+                    .count();
+                (alive * 255 / self.depth.max(1)) as u8
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_rule() {
+        let rule = VolumeRule::parse("4/4/5/M").unwrap();
+        assert_eq!(rule.survival, 1 << 4);
+        assert_eq!(rule.birth, 1 << 4);
+        assert_eq!(rule.states, 5);
+        assert_eq!(rule.neighborhood, Neighborhood3d::Moore);
+
+        let rule = VolumeRule::parse("9-26/5-7,12-13,15/5/M").unwrap();
+        assert_eq!(rule.to_string(), "9-26/5-7,12-13,15/5/M");
+
+        let rule = VolumeRule::parse("/1,3/2/N").unwrap();
+        assert_eq!(rule.survival, 0);
+        assert_eq!(rule.neighborhood, Neighborhood3d::VonNeumann);
+
+        assert!(VolumeRule::parse("4/4/5").is_none());
+        assert!(VolumeRule::parse("4/4/5/X").is_none());
+        assert!(VolumeRule::parse("4/30/5/M").is_none());
+    }
+
+    #[test]
+    fn test_decay() {
+        let rule = VolumeRule::parse("4/4/5/M").unwrap();
+        let mut volume = Volume::new((4, 4, 4));
+        volume.set_value_at_pos((1, 1, 1), true);
+        let idx = volume.pos_to_index((1, 1, 1));
+
+        for state in &[2, 3, 4, 0] {
+            volume.tick(&rule);
+            assert_eq!(volume.cells[idx], *state);
+        }
+        assert_eq!(volume.population(), 0);
+    }
+
+    #[test]
+    fn test_birth() {
+        let rule = VolumeRule::parse("4/4/5/M").unwrap();
+        let mut volume = Volume::new((6, 6, 6));
+        for pos in &[(1, 1, 1), (3, 1, 1), (1, 3, 1), (3, 3, 3)] {
+            volume.set_value_at_pos(*pos, true);
+        }
+
+        volume.tick(&rule);
+        assert!(volume.is_alive((2, 2, 2)));
+    }
+
+    #[test]
+    fn test_views() {
+        let rule = VolumeRule::parse("4/4/5/M").unwrap();
+        let mut volume = Volume::new((2, 2, 4));
+        volume.set_value_at_pos((0, 0, 1), true);
+        volume.set_value_at_pos((0, 0, 3), true);
+
+        volume.slice = 0;
+        assert_eq!(volume.view_intensity((0, 0), &rule), 0);
+        volume.slice = 1;
+        assert_eq!(volume.view_intensity((0, 0), &rule), 255);
+
+        volume.view = VolumeView::MaxProjection;
+        assert_eq!(volume.view_intensity((0, 0), &rule), 255);
+        assert_eq!(volume.view_intensity((1, 0), &rule), 0);
+
+        volume.view = VolumeView::DensityProjection;
+        assert_eq!(volume.view_intensity((0, 0), &rule), 127);
+    }
+}
@@ -0,0 +1,81 @@
+//! Workspace
+//!
+//! Data recorded alongside the game state, kept by the app between frames.
+
+use super::{
+    Census, GameState, History, PopulationHistory, Rule, Timeline, TrackedObject,
+    DEFAULT_MEMORY_BUDGET,
+};
+
+pub struct Workspace {
+    pub history: History,
+    pub timeline: Timeline,
+    /// Last census taken
+    pub census: Option<Census>,
+    pub population: PopulationHistory,
+    /// Object selected in the spaceship detector
+    pub tracked: Option<TrackedObject>,
+    /// Grid size at the last record
+    grid_size: Option<(usize, usize)>,
+}
+
+impl Workspace {
+    pub fn new() -> Self {
+        Self {
+            history: History::new(DEFAULT_MEMORY_BUDGET),
+            timeline: Timeline::default(),
+            census: None,
+            population: PopulationHistory::default(),
+            tracked: None,
+            grid_size: None,
+        }
+    }
+
+    /// Record the game state, to call after each generation and after edits
+    pub fn record(&mut self, state: &GameState) {
+        // Cells moved when the grid was resized
+        if self.grid_size != Some(state.grid_size()) {
+            self.grid_size = Some(state.grid_size());
+            self.tracked = None;
+            self.census = None;
+        }
+
+        self.timeline.record(state);
+        self.population.record(state);
+
+        // Take a census once the grid stabilizes
+        if let Some(periodicity) = state.stats.periodicity {
+            let outdated = self
+                .census
+                .as_ref()
+                .is_none_or(|census| census.generation < periodicity.start);
+            if outdated && state.rule == Rule::Life {
+                self.census = Some(Census::from_state(state));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::Anchor;
+    use super::*;
+
+    #[test]
+    fn test_resize_resets_analysis() {
+        let mut state = GameState::new((8, 8));
+        state.set_value_at_pos((2, 2), true);
+        let mut workspace = Workspace::new();
+        workspace.record(&state);
+
+        workspace.tracked = TrackedObject::pick(&state, (2, 2));
+        workspace.census = Some(Census::from_state(&state));
+        workspace.record(&state);
+        assert!(workspace.tracked.is_some());
+
+        state.resize((10, 10), Anchor::Center);
+        workspace.record(&state);
+        assert!(workspace.tracked.is_none());
+        assert!(workspace.census.is_none());
+    }
+}
@@ -2,7 +2,16 @@
 
 mod app;
 mod gui;
+mod headless;
 mod logic;
 mod renderer;
+mod search;
+// Raw terminal access uses termios
+#[cfg(unix)]
+mod tui;
 
 pub use self::app::run;
+pub use self::headless::run_headless;
+pub use self::search::run_search;
+#[cfg(unix)]
+pub use self::tui::run_tui;
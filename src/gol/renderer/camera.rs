@@ -0,0 +1,140 @@
+//! Camera
+//!
+//! The camera maps grid positions, in cells, to screen positions, in pixels.
+//! It is defined by its zoom, in pixels per cell, and the grid position shown
+//! at the top left corner of the screen.
+
+/// Zoom range, in pixels per cell
+pub const MIN_ZOOM: f32 = 0.25;
+pub const MAX_ZOOM: f32 = 64.0;
+
+/// Camera
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Camera {
+    /// Pixels per cell
+    pub zoom: f32,
+    /// Grid position at the top left corner of the screen, in cells
+    pub position: (f32, f32),
+}
+
+impl Camera {
+    /// Get a camera showing the whole grid, centered on the screen
+    pub fn fit(grid_size: (usize, usize), screen_size: (usize, usize)) -> Self {
+        let (width, height) = (grid_size.0.max(1) as f32, grid_size.1.max(1) as f32);
+        let (screen_width, screen_height) = (screen_size.0 as f32, screen_size.1 as f32);
+        let zoom = (screen_width / width).min(screen_height / height);
+
+        Self::centered(zoom, grid_size, screen_size)
+    }
+
+    /// Get a camera at a given zoom, centered on the grid
+    pub fn centered(zoom: f32, grid_size: (usize, usize), screen_size: (usize, usize)) -> Self {
+        let zoom = zoom.clamp(MIN_ZOOM, MAX_ZOOM);
+
+        Self {
+            zoom,
+            position: (
+                (grid_size.0 as f32 - screen_size.0 as f32 / zoom) / 2.0,
+                (grid_size.1 as f32 - screen_size.1 as f32 / zoom) / 2.0,
+            ),
+        }
+    }
+
+    pub fn screen_to_grid(&self, (x, y): (f32, f32)) -> (f32, f32) {
+        (
+            x / self.zoom + self.position.0,
+            y / self.zoom + self.position.1,
+        )
+    }
+
+    /// Zoom by a factor, keeping the grid position under `anchor` in place
+    pub fn zoom_at(&mut self, factor: f32, anchor: (f32, f32)) {
+        let (x, y) = self.screen_to_grid(anchor);
+        self.zoom = (self.zoom * factor).clamp(MIN_ZOOM, MAX_ZOOM);
+        self.position = (x - anchor.0 / self.zoom, y - anchor.1 / self.zoom);
+    }
+
+    /// Move the view by a distance in pixels
+    pub fn pan(&mut self, (dx, dy): (f32, f32)) {
+        self.position.0 -= dx / self.zoom;
+        self.position.1 -= dy / self.zoom;
+    }
+
+    /// Keep the screen center on the grid
+    pub fn clamp(&mut self, grid_size: (usize, usize), screen_size: (usize, usize)) {
+        let half_width = screen_size.0 as f32 / self.zoom / 2.0;
+        let half_height = screen_size.1 as f32 / self.zoom / 2.0;
+        self.position = (
+            self.position
+                .0
+                .clamp(-half_width, grid_size.0 as f32 - half_width),
+            self.position
+                .1
+                .clamp(-half_height, grid_size.1 as f32 - half_height),
+        );
+    }
+
+    /// Get the cells on screen, clipped to the grid, as `(x0, y0, x1, y1)`
+    /// with exclusive ends
+    pub fn visible_cells(
+        &self,
+        grid_size: (usize, usize),
+        screen_size: (usize, usize),
+    ) -> (usize, usize, usize, usize) {
+        let (x0, y0) = self.screen_to_grid((0.0, 0.0));
+        let (x1, y1) = self.screen_to_grid((screen_size.0 as f32, screen_size.1 as f32));
+        let clip = |value: f32, max: usize| value.clamp(0.0, max as f32) as usize;
+
+        (
+            clip(x0.floor(), grid_size.0),
+            clip(y0.floor(), grid_size.1),
+            clip(x1.ceil(), grid_size.0),
+            clip(y1.ceil(), grid_size.1),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::image::Layout;
+    use super::*;
+
+    fn assert_close((x0, y0): (f32, f32), (x1, y1): (f32, f32)) {
+        assert!(
+            (x0 - x1).abs() < 1e-3 && (y0 - y1).abs() < 1e-3,
+            "{:?} != {:?}",
+            (x0, y0),
+            (x1, y1)
+        );
+    }
+
+    #[test]
+    fn test_screen_to_grid_round_trip() {
+        let camera = Camera {
+            zoom: 3.5,
+            position: (-12.25, 40.0),
+        };
+        let layout = Layout::from(camera);
+
+        for position in [(0.0, 0.0), (17.0, 230.5), (799.0, 12.0)] {
+            let grid = camera.screen_to_grid(position);
+            assert_close(layout.grid_to_image(grid), position);
+            assert_close(layout.image_to_grid(position), grid);
+        }
+    }
+
+    #[test]
+    fn test_zoom_at_keeps_anchor() {
+        let mut camera = Camera::fit((100, 50), (800, 600));
+        let anchor = (123.0, 456.0);
+        let before = camera.screen_to_grid(anchor);
+
+        camera.zoom_at(2.0, anchor);
+        assert_close(camera.screen_to_grid(anchor), before);
+
+        // Clamped zoom still keeps the anchor in place
+        camera.zoom_at(1000.0, anchor);
+        assert_eq!(camera.zoom, MAX_ZOOM);
+        assert_close(camera.screen_to_grid(anchor), before);
+    }
+}
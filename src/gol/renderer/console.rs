@@ -1,58 +1,445 @@
 //! Console renderer module
+//!
+//! Frames are kept as a grid of screen cells. Only the cells which changed
+//! since the previous frame are written, after a cursor move, so streaming
+//! large grids over slow connections stays fast.
+
+use std::fmt::{self, Write as _};
+use std::io::{self, Write};
 
 use super::{GameState, Renderer};
 
+/// Color of newborn cells, darkened as cells get older
+const ALIVE_COLOR: (u8, u8, u8) = (244, 84, 255); // Purple
+
+/// Braille dot bits, by cell position in a 2x4 character
+const BRAILLE_DOTS: [[u32; 2]; 4] = [[0x01, 0x08], [0x02, 0x10], [0x04, 0x20], [0x40, 0x80]];
+
+/// Cells drawn by each character
+#[derive(Clone, Copy, PartialEq, Debug, Default)]
+pub enum ConsoleMode {
+    /// One cell per character
+    #[default]
+    Full,
+    /// Two cells per character, stacked vertically
+    HalfBlock,
+    /// 2x4 cells per character, as Braille dots
+    Braille,
+}
+
+impl ConsoleMode {
+    pub fn all() -> [Self; 3] {
+        [Self::Full, Self::HalfBlock, Self::Braille]
+    }
+
+    /// Get a mode from its name, as shown by `Display`
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::all()
+            .iter()
+            .find(|mode| mode.to_string().eq_ignore_ascii_case(name))
+            .cloned()
+    }
+
+    /// Get the next mode, cycling
+    pub fn next(self) -> Self {
+        match self {
+            Self::Full => Self::HalfBlock,
+            Self::HalfBlock => Self::Braille,
+            Self::Braille => Self::Full,
+        }
+    }
+
+    /// Get the cells drawn by each character, as `(width, height)`
+    pub fn cell_size(self) -> (usize, usize) {
+        match self {
+            Self::Full => (1, 1),
+            Self::HalfBlock => (1, 2),
+            Self::Braille => (2, 4),
+        }
+    }
+}
+
+impl fmt::Display for ConsoleMode {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Full => write!(f, "full"),
+            Self::HalfBlock => write!(f, "half"),
+            Self::Braille => write!(f, "braille"),
+        }
+    }
+}
+
+/// Screen cell, as its escape sequences and character
+type ScreenCell = String;
+
 /// Console renderer
-pub struct ConsoleRenderer;
+pub struct ConsoleRenderer {
+    /// Visible area, as `(x, y, width, height)`, the whole grid if unset
+    pub viewport: Option<(usize, usize, usize, usize)>,
+    /// Highlighted cell
+    pub cursor: Option<(usize, usize)>,
+    /// Status line, shown below the grid
+    pub status: String,
+    pub mode: ConsoleMode,
+    /// Color cells by age, with 24-bit ANSI colors
+    pub color: bool,
+    /// Bytes written for the last frame
+    pub frame_bytes: usize,
+    /// Bytes and frames written so far
+    pub total_bytes: usize,
+    pub frames: usize,
+    frame: Vec<Vec<ScreenCell>>,
+    previous: Vec<Vec<ScreenCell>>,
+    output: String,
+}
 
 impl ConsoleRenderer {
-    pub fn clear_screen(&self) {
-        print!("\x1B[2J\x1B[1;1H");
+    pub fn new() -> Self {
+        Self {
+            viewport: None,
+            cursor: None,
+            status: String::new(),
+            mode: ConsoleMode::Full,
+            color: false,
+            frame_bytes: 0,
+            total_bytes: 0,
+            frames: 0,
+            frame: vec![],
+            previous: vec![],
+            output: String::new(),
+        }
     }
 
-    pub fn draw_header(&self, state: &GameState) {
-        let tick_len = state.current_tick.to_string().len();
-        let header_width = state.width + 2;
+    /// Get the average bytes written per frame
+    pub fn average_frame_bytes(&self) -> usize {
+        self.total_bytes / self.frames.max(1)
+    }
 
-        print!("╔═({})═", state.current_tick);
+    /// Redraw the whole screen on the next frame, after it was resized or
+    /// overwritten
+    pub fn invalidate(&mut self) {
+        self.previous.clear();
+    }
 
-        for _ in 0..header_width - tick_len - 6 {
-            print!("═");
+    /// Get the visible area, clipped to the grid
+    pub fn visible_area(&self, state: &GameState) -> (usize, usize, usize, usize) {
+        match self.viewport {
+            Some((x, y, width, height)) => (x, y, width.min(state.width), height.min(state.height)),
+            None => (0, 0, state.width, state.height),
         }
+    }
+
+    /// Get the size of the visible area in characters, as `(columns, rows)`
+    pub fn text_size(&self, state: &GameState) -> (usize, usize) {
+        let (_, _, width, height) = self.visible_area(state);
+        let (cell_width, cell_height) = self.mode.cell_size();
+        (width.div_ceil(cell_width), height.div_ceil(cell_height))
+    }
 
-        println!("╗");
+    /// Add a line of plain text to the frame
+    fn push_text(&mut self, text: &str) {
+        self.frame.push(text.chars().map(String::from).collect());
     }
 
-    pub fn draw_footer(&self, state: &GameState) {
-        print!("╚");
+    pub fn draw_header(&mut self, state: &GameState) {
+        let (width, _) = self.text_size(state);
+        let tick = state.current_tick.to_string();
 
-        for _ in 0..state.width {
-            print!("═");
+        let mut line = format!("╔═({})═", tick);
+        for _ in 0..(width + 2).saturating_sub(tick.len() + 6) {
+            line.push('═');
         }
+        line.push('╗');
+        self.push_text(&line);
+    }
+
+    pub fn draw_footer(&mut self, state: &GameState) {
+        let (width, _) = self.text_size(state);
 
-        println!("╝");
+        let mut line = String::from("╚");
+        for _ in 0..width {
+            line.push('═');
+        }
+        line.push('╝');
+        self.push_text(&line);
     }
 
-    pub fn draw_line(&self, state: &GameState, y: usize) {
-        print!("║");
-        for x in 0..state.width {
-            let cell = state.data[state.pos_to_index((x, y))];
-            print!("{}", if cell { "█" } else { " " });
+    /// Get the color of a cell by age, if alive
+    fn cell_color(state: &GameState, (x, y): (usize, usize)) -> Option<(u8, u8, u8)> {
+        let idx = state.pos_to_index((x % state.width, y % state.height));
+        if !state.data[idx] {
+            return None;
         }
 
-        println!("║");
+        let age = state.life[idx];
+        Some((
+            ALIVE_COLOR.0.saturating_sub(age),
+            ALIVE_COLOR.1.saturating_sub(age),
+            ALIVE_COLOR.2.saturating_sub(age),
+        ))
     }
-}
 
-impl Renderer for ConsoleRenderer {
-    fn render(&mut self, state: &GameState) {
-        self.clear_screen();
+    /// Set the foreground (38) or background (48) color
+    fn push_color(cell: &mut ScreenCell, layer: u8, (r, g, b): (u8, u8, u8)) {
+        let _ = write!(cell, "\x1B[{};2;{};{};{}m", layer, r, g, b);
+    }
+
+    /// Draw the text line starting at the grid row `y`
+    pub fn draw_line(&mut self, state: &GameState, y: usize) {
+        let (x0, y0, width, height) = self.visible_area(state);
+        let (cell_width, cell_height) = self.mode.cell_size();
+        // Cells past the end of the visible area are not drawn
+        let rows = cell_height.min(y0 + height - y);
+
+        let mut line = vec![String::from("║")];
+        for x in (x0..x0 + width).step_by(cell_width) {
+            let columns = cell_width.min(x0 + width - x);
+            // Cells of the character, with their offset
+            let cells = (0..rows)
+                .flat_map(|dy| (0..columns).map(move |dx| (dx, dy)))
+                .map(|(dx, dy)| ((dx, dy), Self::cell_color(state, (x + dx, y + dy))))
+                .collect::<Vec<_>>();
+            let alive = cells
+                .iter()
+                .filter_map(|(_, color)| *color)
+                .collect::<Vec<_>>();
+            let cursor = self.cursor.is_some_and(|(cx, cy)| {
+                cells.iter().any(|((dx, dy), _)| {
+                    ((x + dx) % state.width, (y + dy) % state.height) == (cx, cy)
+                })
+            });
+
+            let mut cell = ScreenCell::new();
+            if cursor {
+                // Inverse video
+                cell.push_str("\x1B[7m");
+            }
+            if self.color && !alive.is_empty() {
+                // Average color of the live cells
+                let count = alive.len() as u32;
+                let (r, g, b) = alive.iter().fold((0, 0, 0), |(r, g, b), color| {
+                    (
+                        r + u32::from(color.0),
+                        g + u32::from(color.1),
+                        b + u32::from(color.2),
+                    )
+                });
+                Self::push_color(
+                    &mut cell,
+                    38,
+                    ((r / count) as u8, (g / count) as u8, (b / count) as u8),
+                );
+            }
+
+            let glyph = match self.mode {
+                ConsoleMode::Full if cursor => {
+                    if alive.is_empty() {
+                        '+'
+                    } else {
+                        '▓'
+                    }
+                }
+                ConsoleMode::Full => {
+                    if alive.is_empty() {
+                        ' '
+                    } else {
+                        '█'
+                    }
+                }
+                ConsoleMode::HalfBlock => match (cells[0].1, cells.get(1).and_then(|c| c.1)) {
+                    (Some(top), Some(bottom)) if self.color => {
+                        // Upper half in the foreground, lower half in the background
+                        Self::push_color(&mut cell, 38, top);
+                        Self::push_color(&mut cell, 48, bottom);
+                        '▀'
+                    }
+                    (Some(_), Some(_)) => '█',
+                    (Some(_), None) => '▀',
+                    (None, Some(_)) => '▄',
+                    (None, None) => ' ',
+                },
+                ConsoleMode::Braille => {
+                    let dots = cells
+                        .iter()
+                        .filter(|(_, color)| color.is_some())
+                        .map(|((dx, dy), _)| BRAILLE_DOTS[*dy][*dx])
+                        .sum::<u32>();
+                    std::char::from_u32(0x2800 + dots).unwrap_or(' ')
+                }
+            };
+            cell.push(glyph);
+
+            if cursor || (self.color && !alive.is_empty()) {
+                cell.push_str("\x1B[0m");
+            }
+            line.push(cell);
+        }
+        line.push(String::from("║"));
+        self.frame.push(line);
+    }
+
+    /// Write the whole frame
+    fn write_full(&mut self) {
+        self.output.push_str("\x1B[2J\x1B[1;1H");
+        for (row, line) in self.frame.iter().enumerate() {
+            if row > 0 {
+                self.output.push_str("\r\n");
+            }
+            for cell in line {
+                self.output.push_str(cell);
+            }
+        }
+    }
+
+    /// Write the cells changed since the previous frame
+    fn write_changes(&mut self) {
+        // Terminal cursor position, as `(row, column)`
+        let mut position = None;
+
+        for (row, (line, previous)) in self.frame.iter().zip(&self.previous).enumerate() {
+            for (column, cell) in line.iter().enumerate() {
+                if previous.get(column) == Some(cell) {
+                    continue;
+                }
+                if position != Some((row, column)) {
+                    let _ = write!(self.output, "\x1B[{};{}H", row + 1, column + 1);
+                }
+                self.output.push_str(cell);
+                position = Some((row, column + 1));
+            }
+
+            // Erase the end of lines which got shorter
+            if previous.len() > line.len() {
+                let _ = write!(self.output, "\x1B[{};{}H\x1B[K", row + 1, line.len() + 1);
+                position = None;
+            }
+        }
+    }
+
+    /// Encode the current frame in the output, and keep it as the previous one
+    fn encode(&mut self) {
+        if self.previous.len() == self.frame.len() {
+            self.write_changes();
+        } else {
+            self.write_full();
+        }
+        if self.status.is_empty() {
+            // Leave the cursor below the frame, for any following output
+            let _ = write!(self.output, "\x1B[{};1H\r\n", self.frame.len());
+        }
+
+        self.previous = std::mem::take(&mut self.frame);
+    }
+
+    /// Write the current frame, in one write
+    fn flush(&mut self) {
+        self.encode();
+
+        let mut stdout = io::stdout();
+        // Rendering errors, like a closed terminal, are not fatal
+        let _ = stdout.write_all(self.output.as_bytes());
+        let _ = stdout.flush();
+
+        self.frame_bytes = self.output.len();
+        self.total_bytes += self.frame_bytes;
+        self.frames += 1;
+        self.output.clear();
+    }
+
+    /// Draw the whole frame, without writing it
+    fn draw_frame(&mut self, state: &GameState) {
+        let (_, y0, _, height) = self.visible_area(state);
+        let (_, cell_height) = self.mode.cell_size();
+
         self.draw_header(state);
 
-        for y in 0..state.height {
+        for y in (y0..y0 + height).step_by(cell_height) {
             self.draw_line(state, y);
         }
 
         self.draw_footer(state);
+        if !self.status.is_empty() {
+            let status = self.status.clone();
+            self.push_text(&status);
+        }
+    }
+}
+
+impl Renderer for ConsoleRenderer {
+    fn render(&mut self, state: &GameState) {
+        self.draw_frame(state);
+        self.flush();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Draw and encode a frame, returning the output
+    fn encode(renderer: &mut ConsoleRenderer, state: &GameState) -> String {
+        renderer.draw_frame(state);
+        renderer.encode();
+        std::mem::take(&mut renderer.output)
+    }
+
+    /// Get the glyphs of a drawn line, without its borders
+    fn glyphs(renderer: &mut ConsoleRenderer, state: &GameState) -> String {
+        renderer.draw_line(state, 0);
+        let line = renderer.frame.pop().unwrap();
+        line[1..line.len() - 1].concat()
+    }
+
+    #[test]
+    fn test_single_changed_cell() {
+        let mut state = GameState::new((4, 3));
+        let mut renderer = ConsoleRenderer::new();
+        assert!(encode(&mut renderer, &state).starts_with("\x1B[2J"));
+
+        state.set_value_at_pos((2, 1), true);
+        // Header, then the grid line of the cell after the left border
+        assert_eq!(encode(&mut renderer, &state), "\x1B[3;4H█\x1B[5;1H\r\n");
+
+        // Nothing changed
+        assert_eq!(encode(&mut renderer, &state), "\x1B[5;1H\r\n");
+    }
+
+    #[test]
+    fn test_shorter_line_is_erased() {
+        let state = GameState::new((4, 3));
+        let mut renderer = ConsoleRenderer::new();
+        renderer.status = String::from("long status");
+        encode(&mut renderer, &state);
+
+        renderer.status = String::from("short");
+        assert_eq!(
+            encode(&mut renderer, &state),
+            "\x1B[6;1Hshort\x1B[6;6H\x1B[K"
+        );
+    }
+
+    #[test]
+    fn test_half_block_glyphs() {
+        let mut state = GameState::new((4, 2));
+        for pos in [(0, 0), (1, 1), (2, 0), (2, 1)] {
+            state.set_value_at_pos(pos, true);
+        }
+
+        let mut renderer = ConsoleRenderer::new();
+        renderer.mode = ConsoleMode::HalfBlock;
+        assert_eq!(glyphs(&mut renderer, &state), "▀▄█ ");
+    }
+
+    #[test]
+    fn test_braille_bits() {
+        let mut state = GameState::new((4, 4));
+        for pos in [(0, 0), (1, 3), (2, 1), (3, 2)] {
+            state.set_value_at_pos(pos, true);
+        }
+
+        let mut renderer = ConsoleRenderer::new();
+        renderer.mode = ConsoleMode::Braille;
+        // Dots 1 and 8, then dots 2 and 6
+        assert_eq!(glyphs(&mut renderer, &state), "\u{2881}\u{2822}");
     }
 }
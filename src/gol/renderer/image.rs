@@ -1,13 +1,35 @@
 //! Image renderer module
 
-use super::{GameState, Renderer};
+use std::path::Path;
+
+use super::{Camera, GameState, Renderer, Rule, Tiling, Volume, VolumeRule};
+
+/// Minimum cell size for grid lines, in pixels
+const MIN_GRID_LINES_CELL_SIZE: usize = 3;
+
+/// PNG export options
+#[derive(Clone, Copy, Debug)]
+pub struct PngOptions {
+    /// Cell size, in pixels
+    pub cell_size: (usize, usize),
+    /// Draw lines between cells, when cells are large enough
+    pub grid_lines: bool,
+    /// Leave dead cells transparent
+    pub transparent: bool,
+}
 
 /// Image renderer
 pub struct ImageRenderer {
     pub size: (usize, usize),
     pub data: Vec<u8>,
+    /// Draw dead cells with a transparent color
+    pub transparent_dead: bool,
+    /// Visible part of the grid, the whole grid at an integer scale if unset
+    pub camera: Option<Camera>,
     alive_color: (u8, u8, u8),
     dead_color: (u8, u8, u8),
+    grid_color: (u8, u8, u8),
+    species_colors: Vec<(u8, u8, u8)>,
 }
 
 impl ImageRenderer {
@@ -15,8 +37,17 @@ impl ImageRenderer {
         Self {
             size: (0, 0),
             data: vec![],
+            transparent_dead: false,
+            camera: None,
             alive_color: (244, 84, 255), // Purple
             dead_color: (0, 0, 0),       // Black
+            grid_color: (48, 48, 48),    // Gray
+            species_colors: vec![
+                (244, 84, 255), // Purple
+                (84, 220, 255), // Cyan
+                (255, 200, 64), // Yellow
+                (96, 255, 96),  // Green
+            ],
         }
     }
 
@@ -31,21 +62,77 @@ impl ImageRenderer {
         (w, h): (usize, usize),
         color: (u8, u8, u8),
     ) {
+        let w = w.min(self.size.0.saturating_sub(x));
+        let h = h.min(self.size.1.saturating_sub(y));
+        let alpha = if self.transparent_dead && color == self.dead_color {
+            0
+        } else {
+            255
+        };
+
         for oy in 0..h {
             for ox in 0..w {
                 let cur = self.pos_to_index((x + ox, y + oy));
                 self.data[cur] = color.0;
                 self.data[cur + 1] = color.1;
                 self.data[cur + 2] = color.2;
-                self.data[cur + 3] = 255;
+                self.data[cur + 3] = alpha;
             }
         }
     }
 
+    /// Render a grid at a given cell size, for export
+    pub fn snapshot(state: &GameState, options: &PngOptions) -> Self {
+        let (width, height) = state.grid_size();
+        let (cell_width, cell_height) = (options.cell_size.0.max(1), options.cell_size.1.max(1));
+
+        let mut renderer = Self::new();
+        renderer.transparent_dead = options.transparent;
+        renderer.set_size((width * cell_width, height * cell_height));
+        renderer.render(state);
+
+        if options.grid_lines && cell_width.min(cell_height) >= MIN_GRID_LINES_CELL_SIZE {
+            renderer.draw_grid_lines(state);
+        }
+        renderer
+    }
+
+    /// Draw a line on the first row and column of pixels of each cell
+    pub fn draw_grid_lines(&mut self, state: &GameState) {
+        let (cell_width, cell_height) = self.scale(state);
+        let (width, height) = state.grid_size();
+        let color = self.grid_color;
+
+        for x in 0..width {
+            self.draw_rect((x * cell_width, 0), (1, self.size.1), color);
+        }
+        for y in 0..height {
+            self.draw_rect((0, y * cell_height), (self.size.0, 1), color);
+        }
+    }
+
+    /// Save the current image as a PNG file
+    pub fn save_png<P: AsRef<Path>>(&self, path: P) -> ::image::ImageResult<()> {
+        ::image::save_buffer(
+            path,
+            &self.data,
+            self.size.0 as u32,
+            self.size.1 as u32,
+            ::image::ColorType::Rgba8,
+        )
+    }
+
     pub fn pos_to_index(&self, (x, y): (usize, usize)) -> usize {
         x * 4 + y * (self.size.0 * 4)
     }
 
+    pub fn species_color(&self, species: u8) -> (u8, u8, u8) {
+        self.species_colors
+            .get(species as usize)
+            .cloned()
+            .unwrap_or(self.alive_color)
+    }
+
     pub fn darken_color(&self, color: (u8, u8, u8), amount: u8) -> (u8, u8, u8) {
         (
             color.0.saturating_sub(amount),
@@ -55,33 +142,346 @@ impl ImageRenderer {
     }
 
     pub fn scale(&self, state: &GameState) -> (usize, usize) {
+        self.scale_for(state.grid_size())
+    }
+
+    fn scale_for(&self, (width, height): (usize, usize)) -> (usize, usize) {
         (
-            (self.size.0 as f32 / state.width as f32) as usize,
-            (self.size.1 as f32 / state.height as f32) as usize,
+            (self.size.0 as f32 / width as f32) as usize,
+            (self.size.1 as f32 / height as f32) as usize,
         )
     }
-}
 
-impl Renderer for ImageRenderer {
-    fn render(&mut self, state: &GameState) {
-        let (cell_width, cell_height) = self.scale(state);
+    /// Get the mapping between grid cells and image pixels
+    fn layout(&self, grid_size: (usize, usize)) -> Layout {
+        match self.camera {
+            Some(camera) => Layout::from(camera),
+            None => {
+                let (cell_width, cell_height) = self.scale_for(grid_size);
+                Layout {
+                    cell_size: (cell_width.max(1) as f32, cell_height.max(1) as f32),
+                    origin: (0.0, 0.0),
+                }
+            }
+        }
+    }
 
-        for y in 0..state.height {
-            for x in 0..state.width {
-                let idx = state.pos_to_index((x, y));
-                let alive = state.data[idx];
-                let life = state.life[idx];
-                let color = if alive {
-                    self.darken_color(self.alive_color, life)
+    /// Get the cells in the image, as `(x0, y0, x1, y1)` with exclusive ends
+    fn visible_cells(&self, grid_size: (usize, usize)) -> (usize, usize, usize, usize) {
+        match self.camera {
+            Some(camera) => camera.visible_cells(grid_size, self.size),
+            None => (0, 0, grid_size.0, grid_size.1),
+        }
+    }
+
+    /// Fill an area between two pixel positions, clipped to the image
+    fn fill_area(&mut self, (x0, y0): (f32, f32), (x1, y1): (f32, f32), color: (u8, u8, u8)) {
+        let clip = |value: f32, max: usize| value.round().clamp(0.0, max as f32) as usize;
+        let (x0, x1) = (clip(x0, self.size.0), clip(x1, self.size.0));
+        let (y0, y1) = (clip(y0, self.size.1), clip(y1, self.size.1));
+
+        if x1 > x0 && y1 > y0 {
+            self.draw_rect((x0, y0), (x1 - x0, y1 - y0), color);
+        }
+    }
+
+    /// Fill an area given in cells
+    fn fill_cells(
+        &mut self,
+        layout: &Layout,
+        (x, y): (f32, f32),
+        (w, h): (f32, f32),
+        color: (u8, u8, u8),
+    ) {
+        self.fill_area(
+            layout.grid_to_image((x, y)),
+            layout.grid_to_image((x + w, y + h)),
+            color,
+        );
+    }
+
+    /// Get the cell under a screen position, depending on the tiling, or
+    /// `None` outside of the grid
+    pub fn screen_pos_to_cell(
+        &self,
+        state: &GameState,
+        position: (f32, f32),
+    ) -> Option<(usize, usize)> {
+        let layout = self.layout(state.grid_size());
+        let (gx, gy) = layout.image_to_grid(position);
+        let (width, height) = state.grid_size();
+        if gx < 0.0 || gy < 0.0 || gx >= width as f32 || gy >= height as f32 {
+            return None;
+        }
+        let y = (gy as usize).min(height - 1);
+
+        let x = match state.rule.tiling() {
+            Tiling::Square => gx as usize,
+            Tiling::Hexagonal => {
+                let offset = if y % 2 == 1 { layout.row_shift() } else { 0.0 };
+                if gx < offset {
+                    state.width - 1
                 } else {
+                    (gx - offset) as usize
+                }
+            }
+            Tiling::Triangular => {
+                let frac = gy - y as f32;
+                let x = gx as usize;
+                (x.saturating_sub(1)..=x + 1)
+                    .find(|x| {
+                        let (start, end) = Self::triangle_span((*x, y), frac);
+                        gx >= start && gx < end
+                    })
+                    .unwrap_or(x)
+            }
+        };
+
+        Some((x.min(width - 1), y))
+    }
+
+    /// Get the horizontal span of a triangle at a fraction of its height, in
+    /// cells
+    fn triangle_span((x, y): (usize, usize), frac: f32) -> (f32, f32) {
+        let center = x as f32 + 0.5;
+        let half_width = if Tiling::is_triangle_up((x, y)) {
+            frac
+        } else {
+            1.0 - frac
+        };
+
+        (center - half_width, center + half_width)
+    }
+
+    fn cell_color(&self, cells: &Cells, (x, y): (usize, usize)) -> (u8, u8, u8) {
+        let state = match cells {
+            Cells::State(state) => state,
+            Cells::Colors { width, colors } => {
+                let start = (x + y * width) * 3;
+                return (colors[start], colors[start + 1], colors[start + 2]);
+            }
+        };
+
+        let idx = state.pos_to_index((x, y));
+        let alive = state.data[idx];
+        let life = state.life[idx];
+
+        if alive && state.rule.species_count() > 1 {
+            self.darken_color(self.species_color(state.species[idx]), life)
+        } else if alive {
+            self.darken_color(self.alive_color, life)
+        } else {
+            self.dead_color
+        }
+    }
+
+    /// Draw a grid of RGB cell colors, with a tiling, at an integer scale
+    pub fn render_cells(&mut self, tiling: Tiling, size: (usize, usize), colors: &[u8]) {
+        let cells = Cells::Colors {
+            width: size.0,
+            colors,
+        };
+        // Cells are only part of the grid, so shifted cells do not wrap
+        self.render_tiling(tiling, &cells, size, false);
+    }
+
+    fn render_tiling(&mut self, tiling: Tiling, cells: &Cells, size: (usize, usize), wrap: bool) {
+        match tiling {
+            Tiling::Square => self.render_square(cells, size),
+            Tiling::Hexagonal => self.render_hexagonal(cells, size, wrap),
+            Tiling::Triangular => self.render_triangular(cells, size),
+        }
+    }
+
+    fn render_volume(&mut self, state: &GameState, volume: &Volume, rule: &VolumeRule) {
+        let layout = self.layout(state.grid_size());
+        let (x0, y0, x1, y1) = self.visible_cells(state.grid_size());
+
+        for y in y0..y1.min(volume.height) {
+            for x in x0..x1.min(volume.width) {
+                let intensity = volume.view_intensity((x, y), rule) as usize;
+                let color = if intensity == 0 {
                     self.dead_color
+                } else {
+                    (
+                        (self.alive_color.0 as usize * intensity / 255) as u8,
+                        (self.alive_color.1 as usize * intensity / 255) as u8,
+                        (self.alive_color.2 as usize * intensity / 255) as u8,
+                    )
                 };
-                self.draw_rect(
-                    (x * cell_width, y * cell_height),
-                    (cell_width, cell_height),
+                self.fill_cells(&layout, (x as f32, y as f32), (1.0, 1.0), color);
+            }
+        }
+    }
+
+    fn render_square(&mut self, cells: &Cells, size: (usize, usize)) {
+        let layout = self.layout(size);
+        let (x0, y0, x1, y1) = self.visible_cells(size);
+
+        for y in y0..y1 {
+            for x in x0..x1 {
+                let color = self.cell_color(cells, (x, y));
+                self.fill_cells(&layout, (x as f32, y as f32), (1.0, 1.0), color);
+            }
+        }
+    }
+
+    fn render_hexagonal(&mut self, cells: &Cells, size: (usize, usize), wrap: bool) {
+        let layout = self.layout(size);
+        let (x0, y0, x1, y1) = self.visible_cells(size);
+        let width = size.0 as f32;
+
+        for y in y0..y1 {
+            let offset = if y % 2 == 1 { layout.row_shift() } else { 0.0 };
+
+            // Shifted cells start in the previous column
+            for x in x0.saturating_sub(1)..x1 {
+                let color = self.cell_color(cells, (x, y));
+                let start = x as f32 + offset;
+                self.fill_cells(
+                    &layout,
+                    (start, y as f32),
+                    ((width - start).min(1.0), 1.0),
                     color,
                 );
             }
+
+            // Wrap the last shifted cell on the left side
+            if wrap && offset > 0.0 && x0 == 0 {
+                let color = self.cell_color(cells, (size.0 - 1, y));
+                self.fill_cells(&layout, (0.0, y as f32), (offset, 1.0), color);
+            }
+        }
+    }
+
+    fn render_triangular(&mut self, cells: &Cells, size: (usize, usize)) {
+        let layout = self.layout(size);
+        let (x0, y0, x1, y1) = self.visible_cells(size);
+        let width = size.0 as f32;
+
+        for y in y0..y1 {
+            self.fill_cells(
+                &layout,
+                (x0 as f32, y as f32),
+                ((x1 - x0) as f32, 1.0),
+                self.dead_color,
+            );
+
+            let (_, top) = layout.grid_to_image((0.0, y as f32));
+            let (_, bottom) = layout.grid_to_image((0.0, y as f32 + 1.0));
+            let clip = |value: f32| value.round().clamp(0.0, self.size.1 as f32) as usize;
+            let rows = clip(top)..clip(bottom);
+
+            // Triangles overlap the neighbor columns
+            for x in x0.saturating_sub(1)..(x1 + 1).min(size.0) {
+                let color = self.cell_color(cells, (x, y));
+                if color == self.dead_color {
+                    continue;
+                }
+
+                for py in rows.clone() {
+                    let frac = (py as f32 + 0.5 - top) / (bottom - top);
+                    let (start, end) = Self::triangle_span((x, y), frac);
+                    let (start, end) = (start.max(0.0), end.min(width));
+                    if end > start {
+                        let (start, _) = layout.grid_to_image((start, 0.0));
+                        let (end, _) = layout.grid_to_image((end, 0.0));
+                        self.fill_area((start, py as f32), (end, py as f32 + 1.0), color);
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Source of the cell colors
+enum Cells<'a> {
+    State(&'a GameState),
+    /// RGB colors, row by row
+    Colors {
+        width: usize,
+        colors: &'a [u8],
+    },
+}
+
+/// Mapping between grid cells and image pixels
+pub(super) struct Layout {
+    /// Cell size, in pixels
+    cell_size: (f32, f32),
+    /// Grid position at the top left corner of the image, in cells
+    origin: (f32, f32),
+}
+
+impl From<Camera> for Layout {
+    fn from(camera: Camera) -> Self {
+        Self {
+            cell_size: (camera.zoom, camera.zoom),
+            origin: camera.position,
+        }
+    }
+}
+
+impl Layout {
+    pub(super) fn grid_to_image(&self, (x, y): (f32, f32)) -> (f32, f32) {
+        (
+            (x - self.origin.0) * self.cell_size.0,
+            (y - self.origin.1) * self.cell_size.1,
+        )
+    }
+
+    /// Get the shift of odd hexagonal rows, half a cell rounded down to
+    /// whole pixels
+    fn row_shift(&self) -> f32 {
+        (self.cell_size.0 / 2.0).floor() / self.cell_size.0
+    }
+
+    pub(super) fn image_to_grid(&self, (x, y): (f32, f32)) -> (f32, f32) {
+        (
+            x / self.cell_size.0 + self.origin.0,
+            y / self.cell_size.1 + self.origin.1,
+        )
+    }
+}
+
+impl Renderer for ImageRenderer {
+    fn render(&mut self, state: &GameState) {
+        if self.camera.is_some() {
+            // Clear the area around the grid
+            self.data.fill(0);
         }
+
+        if let (Rule::Volume(rule), Some(volume)) = (state.rule, state.active_volume()) {
+            return self.render_volume(state, volume, &rule);
+        }
+
+        self.render_tiling(
+            state.rule.tiling(),
+            &Cells::State(state),
+            (state.width, state.height),
+            true,
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_screen_pos_outside_grid() {
+        let state = GameState::new((10, 10));
+        let mut renderer = ImageRenderer::new();
+        renderer.set_size((200, 200));
+        renderer.camera = Some(Camera {
+            zoom: 10.0,
+            position: (-5.0, -5.0),
+        });
+
+        assert_eq!(
+            renderer.screen_pos_to_cell(&state, (75.0, 55.0)),
+            Some((2, 0))
+        );
+        assert_eq!(renderer.screen_pos_to_cell(&state, (20.0, 75.0)), None);
+        assert_eq!(renderer.screen_pos_to_cell(&state, (75.0, 160.0)), None);
     }
 }
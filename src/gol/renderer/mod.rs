@@ -1,9 +1,11 @@
 // Renderer module
 
-use super::logic::GameState;
+use super::logic::{GameState, Rule, Tiling, Volume, VolumeRule};
 
+mod camera;
 mod console;
 mod image;
+mod recording;
 
 /// Renderer
 pub trait Renderer {
@@ -11,5 +13,7 @@ pub trait Renderer {
     fn render(&mut self, state: &GameState);
 }
 
-pub use self::console::ConsoleRenderer;
-pub use self::image::ImageRenderer;
+pub use self::camera::{Camera, MAX_ZOOM, MIN_ZOOM};
+pub use self::console::{ConsoleMode, ConsoleRenderer};
+pub use self::image::{ImageRenderer, PngOptions};
+pub use self::recording::{Recording, RecordingOptions, RECORDING_MEMORY_BUDGET};
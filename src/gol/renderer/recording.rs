@@ -0,0 +1,536 @@
+//! Animation recording
+//!
+//! Frames are kept as the colors of the cells, optionally cropped to the
+//! pattern bounding box, and only scaled to the cell size when the animation
+//! is saved. All frames share the union of their boxes, so objects do not
+//! jump around in the animation.
+//!
+//! GIF frames use a palette of at most 256 colors: the exact colors when
+//! there are few enough of them, a NeuQuant quantization otherwise. APNG
+//! files keep the exact colors, and are written chunk by chunk.
+
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+
+use super::{GameState, ImageRenderer, PngOptions, Tiling};
+
+/// Memory budget of the recorded frames, in bytes
+pub const RECORDING_MEMORY_BUDGET: usize = 256 * 1024 * 1024;
+
+/// Background color, outside of cropped frames
+const BACKGROUND: [u8; 4] = [0, 0, 0, 255];
+
+/// Frame area, as `(x, y, width, height)` in cells
+type Area = (usize, usize, usize, usize);
+
+/// PNG file signature
+const PNG_SIGNATURE: [u8; 8] = [137, 80, 78, 71, 13, 10, 26, 10];
+
+/// NeuQuant sampling factor, from 1 (best) to 30 (fastest)
+const QUANTIZATION_SAMPLING: i32 = 10;
+
+/// Animation file format
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum AnimationFormat {
+    Gif,
+    Apng,
+}
+
+impl AnimationFormat {
+    /// Get the format from a file extension: `.gif`, or `.png` and `.apng`
+    pub fn from_path<P: AsRef<Path>>(path: P) -> Option<Self> {
+        let extension = path.as_ref().extension()?.to_str()?.to_ascii_lowercase();
+        match extension.as_str() {
+            "gif" => Some(Self::Gif),
+            "png" | "apng" => Some(Self::Apng),
+            _ => None,
+        }
+    }
+}
+
+/// Recording options
+#[derive(Clone, Copy, Debug)]
+pub struct RecordingOptions {
+    /// Cell size, in pixels
+    pub cell_size: usize,
+    /// Generations between frames
+    pub stride: usize,
+    /// Delay between frames, in milliseconds
+    pub delay: u16,
+    /// Crop frames to the pattern bounding box
+    pub crop: bool,
+}
+
+/// Recorded frame, as the RGB colors of an area of the grid
+struct Frame {
+    /// Position on the grid, in cells
+    origin: (usize, usize),
+    size: (usize, usize),
+    colors: Vec<u8>,
+}
+
+/// Animation recording
+pub struct Recording {
+    pub options: RecordingOptions,
+    frames: Vec<Frame>,
+    /// Grid size, in cells
+    size: (usize, usize),
+    tiling: Tiling,
+    /// Generation of the last frame
+    last_tick: Option<usize>,
+    memory_used: usize,
+}
+
+impl Recording {
+    pub fn new(options: RecordingOptions) -> Self {
+        Self {
+            options,
+            frames: vec![],
+            size: (0, 0),
+            tiling: Tiling::Square,
+            last_tick: None,
+            memory_used: 0,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.frames.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.frames.is_empty()
+    }
+
+    /// Get the memory used by the recorded frames, in bytes
+    pub fn memory_used(&self) -> usize {
+        self.memory_used
+    }
+
+    pub fn is_full(&self) -> bool {
+        self.memory_used >= RECORDING_MEMORY_BUDGET
+    }
+
+    /// Record a frame if the generation changed, every `stride` generations
+    pub fn record(&mut self, state: &GameState) {
+        let tick = state.current_tick;
+        let stride = self.options.stride.max(1);
+        let due = match self.last_tick {
+            Some(last) => tick != last && (tick.max(last) - tick.min(last)) >= stride,
+            None => true,
+        };
+
+        if due && !self.is_full() {
+            self.capture(state);
+            self.last_tick = Some(tick);
+        }
+    }
+
+    /// Record a frame
+    pub fn capture(&mut self, state: &GameState) {
+        // One pixel per cell, whatever the tiling
+        let options = PngOptions {
+            cell_size: (1, 1),
+            grid_lines: false,
+            transparent: false,
+        };
+        let image = ImageRenderer::snapshot(state, &options);
+        self.size = image.size;
+        self.tiling = state.rule.tiling();
+
+        let (origin, size) = match (self.options.crop, state.stats.bounding_box) {
+            (true, Some((min_x, min_y, max_x, max_y))) => {
+                let (mut min_x, mut min_y, mut max_x) = (min_x, min_y, max_x);
+                if self.tiling != Tiling::Square {
+                    // Keep the parity of the rows and columns, which sets
+                    // the shape of hexagonal and triangular cells
+                    min_x -= min_x % 2;
+                    min_y -= min_y % 2;
+                }
+                if self.tiling == Tiling::Hexagonal {
+                    // Shifted rows overlap the next column
+                    max_x = (max_x + 1).min(self.size.0 - 1);
+                }
+                ((min_x, min_y), (max_x - min_x + 1, max_y - min_y + 1))
+            }
+            (true, None) => ((0, 0), (0, 0)),
+            (false, _) => ((0, 0), self.size),
+        };
+
+        let mut colors = Vec::with_capacity(size.0 * size.1 * 3);
+        for y in origin.1..origin.1 + size.1 {
+            for x in origin.0..origin.0 + size.0 {
+                let start = image.pos_to_index((x, y));
+                colors.extend_from_slice(&image.data[start..start + 3]);
+            }
+        }
+
+        self.memory_used += colors.len();
+        self.frames.push(Frame {
+            origin,
+            size,
+            colors,
+        });
+    }
+
+    /// Get the area shared by all frames, as `(x, y, width, height)`
+    fn area(&self) -> Area {
+        // Frames of empty grids are cropped to nothing
+        let mut frames = self
+            .frames
+            .iter()
+            .filter(|frame| frame.size.0 > 0 && frame.size.1 > 0);
+        let first = match frames.next() {
+            Some(frame) => frame,
+            None => return (0, 0, self.size.0, self.size.1),
+        };
+
+        let (mut min_x, mut min_y) = first.origin;
+        let (mut max_x, mut max_y) = (min_x + first.size.0, min_y + first.size.1);
+        for frame in frames {
+            min_x = min_x.min(frame.origin.0);
+            min_y = min_y.min(frame.origin.1);
+            max_x = max_x.max(frame.origin.0 + frame.size.0);
+            max_y = max_y.max(frame.origin.1 + frame.size.1);
+        }
+
+        (min_x, min_y, max_x - min_x, max_y - min_y)
+    }
+
+    /// Get the image size, in pixels
+    fn image_size(&self, (_, _, width, height): Area) -> (usize, usize) {
+        let cell_size = self.options.cell_size.max(1);
+        (width * cell_size, height * cell_size)
+    }
+
+    /// Get a frame as a RGBA image covering the shared area
+    fn image(&self, frame: &Frame, area: Area) -> Vec<u8> {
+        let (x0, y0, width, height) = area;
+        let mut colors = BACKGROUND[..3].repeat(width * height);
+        if frame.size.0 > 0 {
+            let (ox, oy) = (frame.origin.0 - x0, frame.origin.1 - y0);
+            for (y, row) in frame.colors.chunks(frame.size.0 * 3).enumerate() {
+                let start = (ox + (oy + y) * width) * 3;
+                colors[start..start + row.len()].copy_from_slice(row);
+            }
+        }
+
+        let size = self.image_size(area);
+        let mut renderer = ImageRenderer::new();
+        renderer.set_size(size);
+        renderer.data = BACKGROUND.repeat(size.0 * size.1);
+        renderer.render_cells(self.tiling, (width, height), &colors);
+        renderer.data
+    }
+
+    /// Save the animation, in the format matching the file extension
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), String> {
+        let format = AnimationFormat::from_path(&path)
+            .ok_or_else(|| "Unknown animation format, use .gif or .png".to_string())?;
+        if self.is_empty() {
+            return Err("No frames recorded".to_string());
+        }
+
+        let area = self.area();
+        let (width, height) = self.image_size(area);
+        if format == AnimationFormat::Gif && (width > 0xFFFF || height > 0xFFFF) {
+            return Err(format!(
+                "Frames are too large for GIF: {}x{}",
+                width, height
+            ));
+        }
+
+        let file = File::create(&path).map_err(|e| e.to_string())?;
+        let mut writer = BufWriter::new(file);
+        match format {
+            AnimationFormat::Gif => self.write_gif(&mut writer, area),
+            AnimationFormat::Apng => self.write_apng(&mut writer, area),
+        }
+        .and_then(|_| writer.flush())
+        .map_err(|e| e.to_string())
+    }
+
+    fn write_gif<W: Write>(&self, writer: W, area: Area) -> io::Result<()> {
+        let (width, height) = self.image_size(area);
+        let palette = Palette::new(&self.frames);
+        let mut encoder = gif::Encoder::new(writer, width as u16, height as u16, &palette.rgb())?;
+        encoder.write_extension(gif::ExtensionData::Repetitions(gif::Repeat::Infinite))?;
+
+        for frame in &self.frames {
+            let image = self.image(frame, area);
+            let frame = gif::Frame {
+                // In units of 10 ms
+                delay: self.options.delay / 10,
+                width: width as u16,
+                height: height as u16,
+                buffer: Cow::Owned(palette.indices(&image)),
+                ..gif::Frame::default()
+            };
+            encoder.write_frame(&frame)?;
+        }
+
+        Ok(())
+    }
+
+    fn write_apng<W: Write>(&self, mut writer: W, area: Area) -> io::Result<()> {
+        let (width, height) = self.image_size(area);
+        let (width, height) = (width as u32, height as u32);
+        writer.write_all(&PNG_SIGNATURE)?;
+
+        // 8 bits RGBA, no interlacing
+        let mut header = [width.to_be_bytes(), height.to_be_bytes()].concat();
+        header.extend_from_slice(&[8, 6, 0, 0, 0]);
+        write_chunk(&mut writer, b"IHDR", &header)?;
+
+        // Frame count, infinite loop
+        let control = [(self.frames.len() as u32).to_be_bytes(), 0u32.to_be_bytes()].concat();
+        write_chunk(&mut writer, b"acTL", &control)?;
+
+        let mut sequence = 0u32;
+        for (index, frame) in self.frames.iter().enumerate() {
+            let image = self.image(frame, area);
+            let mut frame_control = vec![];
+            for value in &[sequence, width, height, 0, 0] {
+                frame_control.extend_from_slice(&value.to_be_bytes());
+            }
+            // Delay in milliseconds, no disposal, no blending
+            frame_control.extend_from_slice(&self.options.delay.to_be_bytes());
+            frame_control.extend_from_slice(&1000u16.to_be_bytes());
+            frame_control.extend_from_slice(&[0, 0]);
+            write_chunk(&mut writer, b"fcTL", &frame_control)?;
+            sequence += 1;
+
+            // Each row starts with its filter type, none
+            let mut raw = Vec::with_capacity(image.len() + height as usize);
+            for row in image.chunks(width as usize * 4) {
+                raw.push(0);
+                raw.extend_from_slice(row);
+            }
+            let compressed = deflate::deflate_bytes_zlib(&raw);
+
+            if index == 0 {
+                write_chunk(&mut writer, b"IDAT", &compressed)?;
+            } else {
+                let mut data = sequence.to_be_bytes().to_vec();
+                data.extend_from_slice(&compressed);
+                write_chunk(&mut writer, b"fdAT", &data)?;
+                sequence += 1;
+            }
+        }
+
+        write_chunk(&mut writer, b"IEND", &[])
+    }
+}
+
+/// Write a PNG chunk, with its length and checksum
+fn write_chunk<W: Write>(writer: &mut W, kind: &[u8; 4], data: &[u8]) -> io::Result<()> {
+    let mut hasher = crc32fast::Hasher::new();
+    hasher.update(kind);
+    hasher.update(data);
+
+    writer.write_all(&(data.len() as u32).to_be_bytes())?;
+    writer.write_all(kind)?;
+    writer.write_all(data)?;
+    writer.write_all(&hasher.finalize().to_be_bytes())
+}
+
+/// GIF palette
+enum Palette {
+    /// Exact colors, in RGBA, with their index
+    Exact(HashMap<[u8; 4], u8>),
+    Quantized(color_quant::NeuQuant),
+}
+
+impl Palette {
+    fn new(frames: &[Frame]) -> Self {
+        let mut colors = HashMap::new();
+        colors.insert(BACKGROUND, 0);
+
+        for cell in frames.iter().flat_map(|frame| frame.colors.chunks(3)) {
+            let index = colors.len();
+            colors
+                .entry([cell[0], cell[1], cell[2], 255])
+                .or_insert(index);
+        }
+
+        if colors.len() <= 256 {
+            return Self::Exact(
+                colors
+                    .into_iter()
+                    .map(|(color, index)| (color, index as u8))
+                    .collect(),
+            );
+        }
+
+        // Quantize the distinct colors, in a stable order
+        let mut distinct = colors.into_keys().collect::<Vec<_>>();
+        distinct.sort_unstable();
+        Self::Quantized(color_quant::NeuQuant::new(
+            QUANTIZATION_SAMPLING,
+            256,
+            &distinct.concat(),
+        ))
+    }
+
+    /// Get the palette colors, in RGB
+    fn rgb(&self) -> Vec<u8> {
+        let mut rgb = match self {
+            Self::Exact(colors) => {
+                let mut rgb = vec![0; colors.len() * 3];
+                for (color, index) in colors {
+                    let start = *index as usize * 3;
+                    rgb[start..start + 3].copy_from_slice(&color[..3]);
+                }
+                rgb
+            }
+            Self::Quantized(quantizer) => quantizer.color_map_rgb(),
+        };
+        // At least two colors
+        rgb.resize(rgb.len().max(6), 0);
+        rgb
+    }
+
+    /// Get the palette index of each pixel
+    fn indices(&self, image: &[u8]) -> Vec<u8> {
+        image
+            .chunks(4)
+            .map(|pixel| match self {
+                Self::Exact(colors) => colors[&[pixel[0], pixel[1], pixel[2], pixel[3]]],
+                Self::Quantized(quantizer) => quantizer.index_of(pixel) as u8,
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gol::logic::GameStats;
+
+    fn options(crop: bool) -> RecordingOptions {
+        RecordingOptions {
+            cell_size: 2,
+            stride: 1,
+            delay: 100,
+            crop,
+        }
+    }
+
+    /// Get a blinker, horizontal at generation 0
+    fn blinker() -> GameState {
+        let mut state = GameState::new((16, 16));
+        for x in 5..8 {
+            state.set_value_at_pos((x, 4), true);
+        }
+        state.stats = GameStats::from_state(&state);
+        state
+    }
+
+    #[test]
+    fn test_crop_area() {
+        let mut state = blinker();
+        let mut recording = Recording::new(options(true));
+        recording.record(&state);
+        state.tick();
+        recording.record(&state);
+
+        assert_eq!(recording.frames[0].origin, (5, 4));
+        assert_eq!(recording.frames[0].size, (3, 1));
+        assert_eq!(recording.frames[1].origin, (6, 3));
+        assert_eq!(recording.frames[1].size, (1, 3));
+        // Frames are kept in cells, as RGB
+        assert_eq!(recording.memory_used(), (3 + 3) * 3);
+
+        let area = recording.area();
+        assert_eq!(area, (5, 3, 3, 3));
+        assert_eq!(recording.image_size(area), (6, 6));
+        assert_eq!(recording.image(&recording.frames[0], area).len(), 6 * 6 * 4);
+
+        let mut uncropped = Recording::new(options(false));
+        uncropped.record(&state);
+        assert_eq!(uncropped.area(), (0, 0, 16, 16));
+    }
+
+    #[test]
+    fn test_apng_chunks() {
+        let mut state = blinker();
+        let mut recording = Recording::new(options(true));
+        for _ in 0..3 {
+            recording.record(&state);
+            state.tick();
+        }
+
+        let mut output = vec![];
+        recording.write_apng(&mut output, recording.area()).unwrap();
+        assert_eq!(output[..8], PNG_SIGNATURE);
+
+        let be32 = |bytes: &[u8]| u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]);
+        let mut chunks = vec![];
+        let mut sequence = vec![];
+        let mut position = 8;
+        while position < output.len() {
+            let length = be32(&output[position..]) as usize;
+            let kind = String::from_utf8_lossy(&output[position + 4..position + 8]).to_string();
+            let data = &output[position + 8..position + 8 + length];
+            match kind.as_str() {
+                "IHDR" => assert_eq!((be32(data), be32(&data[4..])), (6, 6)),
+                // Frame count
+                "acTL" => assert_eq!(be32(data), 3),
+                "fcTL" | "fdAT" => sequence.push(be32(data)),
+                _ => {}
+            }
+            chunks.push(kind);
+            position += length + 12;
+        }
+
+        assert_eq!(
+            chunks,
+            vec!["IHDR", "acTL", "fcTL", "IDAT", "fcTL", "fdAT", "fcTL", "fdAT", "IEND"]
+        );
+        assert_eq!(sequence, vec![0, 1, 2, 3, 4]);
+    }
+
+    #[test]
+    fn test_apng_decodes() {
+        let mut state = blinker();
+        let mut recording = Recording::new(options(true));
+        for _ in 0..3 {
+            recording.record(&state);
+            state.tick();
+        }
+
+        let mut output = vec![];
+        let area = recording.area();
+        recording.write_apng(&mut output, area).unwrap();
+
+        // Checks chunk checksums, sequence numbers and compressed data, frame
+        // by frame
+        let mut decoder = png::StreamingDecoder::new();
+        let mut frames: Vec<Vec<u8>> = vec![];
+        let mut data = vec![];
+        let mut input = output.as_slice();
+        while !input.is_empty() {
+            let (consumed, decoded) = decoder.update(input, &mut data).unwrap();
+            input = &input[consumed..];
+            match decoded {
+                png::Decoded::Header(width, height, ..) => assert_eq!((width, height), (6, 6)),
+                png::Decoded::AnimationControl(control) => assert_eq!(control.num_frames, 3),
+                png::Decoded::FrameControl(_) => frames.push(vec![]),
+                png::Decoded::ImageData => frames.last_mut().unwrap().append(&mut data),
+                _ => {}
+            }
+        }
+
+        assert_eq!(frames.len(), 3);
+        for (frame, raw) in recording.frames.iter().zip(frames) {
+            // Rows start with their filter type
+            let image = raw
+                .chunks(6 * 4 + 1)
+                .flat_map(|row| row[1..].to_vec())
+                .collect::<Vec<_>>();
+            assert_eq!(image, recording.image(frame, area));
+        }
+    }
+}
@@ -0,0 +1,108 @@
+//! Headless soup search
+
+use std::fs;
+use std::time::Instant;
+
+use super::logic::{Search, SearchConfig};
+
+/// Soups run between progress reports
+const BATCH_SIZE: u64 = 1000;
+
+const USAGE: &str = "Usage: caw search [options]
+
+Options:
+    --soups <count>            Number of soups to run (default: 10000)
+    --seed <seed>              Base seed, soup n uses seed + n (default: 0)
+    --soup-size <cells>        Soup side (default: 16)
+    --area-size <cells>        Area side (default: 64)
+    --max-generations <count>  Generation limit per soup (default: 4000)
+    --threads <count>          Worker threads (default: CPU count)
+    --report <file>            Report file (default: search-report.txt)
+    --rare <count>             List objects seen at most this many times (default: 10)";
+
+struct SearchArgs {
+    config: SearchConfig,
+    soups: u64,
+    report: String,
+    rare: usize,
+}
+
+fn parse_args(args: &[String]) -> Result<SearchArgs, String> {
+    let mut parsed = SearchArgs {
+        config: SearchConfig::default(),
+        soups: 10000,
+        report: "search-report.txt".to_string(),
+        rare: 10,
+    };
+
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        let mut value = || {
+            args.next()
+                .ok_or_else(|| format!("Missing value for {}", arg))
+        };
+        let number = |value: &String| {
+            value
+                .parse::<u64>()
+                .map_err(|_| format!("Invalid value for {}: {}", arg, value))
+        };
+
+        match arg.as_str() {
+            "--soups" => parsed.soups = number(value()?)?,
+            "--seed" => parsed.config.seed = number(value()?)?,
+            "--soup-size" => parsed.config.soup_size = number(value()?)? as usize,
+            "--area-size" => parsed.config.area_size = number(value()?)? as usize,
+            "--max-generations" => parsed.config.max_generations = number(value()?)? as usize,
+            "--threads" => parsed.config.threads = number(value()?)?.max(1) as usize,
+            "--report" => parsed.report = value()?.clone(),
+            "--rare" => parsed.rare = number(value()?)? as usize,
+            "-h" | "--help" => return Err(String::new()),
+            _ => return Err(format!("Unknown option: {}", arg)),
+        }
+    }
+
+    if parsed.config.soup_size == 0 || parsed.config.soup_size > parsed.config.area_size {
+        return Err("Soup size must be between 1 and the area size".to_string());
+    }
+
+    Ok(parsed)
+}
+
+/// Run a soup search from command line arguments, returning the exit code
+pub fn run_search(args: &[String]) -> i32 {
+    let args = match parse_args(args) {
+        Ok(args) => args,
+        Err(e) if e.is_empty() => {
+            println!("{}", USAGE);
+            return 0;
+        }
+        Err(e) => {
+            eprintln!("{}\n\n{}", e, USAGE);
+            return 1;
+        }
+    };
+
+    let mut search = Search::new(args.config);
+    let start = Instant::now();
+
+    while search.soups < args.soups {
+        let count = BATCH_SIZE.min(args.soups - search.soups);
+        search.run_batch(count);
+
+        let elapsed = start.elapsed().as_secs_f32();
+        eprintln!(
+            "{} soups, {} objects, {:.1} soups/s",
+            search.soups,
+            search.total(),
+            search.soups as f32 / elapsed.max(0.001)
+        );
+    }
+
+    if let Err(e) = fs::write(&args.report, search.report(args.rare)) {
+        eprintln!("Could not write {}: {}", args.report, e);
+        return 1;
+    }
+    eprintln!("Report written to {}", args.report);
+
+    0
+}
@@ -0,0 +1,352 @@
+//! Interactive terminal mode
+//!
+//! Runs the simulation in the terminal, for machines without a display like
+//! over SSH. The grid can be larger than the terminal, the view scrolls to
+//! follow the cursor.
+
+mod terminal;
+
+use std::time::Instant;
+
+use self::terminal::{Key, RawTerminal};
+use super::logic::{GameState, GameStats, Rule, MAX_SPEED, MIN_SPEED};
+use super::renderer::{ConsoleMode, ConsoleRenderer, Renderer};
+
+const USAGE: &str = "Usage: caw tui [options]
+
+Options:
+    --rule <name>           Rule name or notation (default: B3/S23)
+    --seed <seed>           Initial soup seed (default: random)
+    --density <density>     Soup density, from 0 to 1 (default: 0.5)
+    --size <width>x<height> Grid size (default: terminal size)
+    --mode <mode>           Cells per character: full, half or braille (default: full)
+    --color                 Color cells by age, needs a 24-bit color terminal
+
+Keys:
+    space                   Pause or resume
+    s, backspace            Step forward, step back
+    + -                     Change speed
+    r, c                    Randomize, clear
+    arrows, h j k l         Move the cursor
+    page up, page down      Move the cursor by a page
+    t, enter                Toggle the cell under the cursor
+    m                       Switch between full, half and braille modes
+    a                       Toggle colors by age
+    q, escape               Quit";
+
+/// Delay between frames, in milliseconds
+const FRAME_DELAY: i32 = 33;
+
+/// Terminal rows and columns used around the grid
+const BORDER_ROWS: usize = 3;
+const BORDER_COLUMNS: usize = 2;
+
+struct TuiArgs {
+    rule: Option<String>,
+    seed: Option<u64>,
+    density: Option<f32>,
+    size: Option<(usize, usize)>,
+    mode: ConsoleMode,
+    color: bool,
+}
+
+fn parse_args(args: &[String]) -> Result<TuiArgs, String> {
+    let mut parsed = TuiArgs {
+        rule: None,
+        seed: None,
+        density: None,
+        size: None,
+        mode: ConsoleMode::Full,
+        color: false,
+    };
+
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        let mut value = || {
+            args.next()
+                .cloned()
+                .ok_or_else(|| format!("Missing value for {}", arg))
+        };
+        let invalid = |value: &str| format!("Invalid value for {}: {}", arg, value);
+
+        match arg.as_str() {
+            "--rule" => parsed.rule = Some(value()?),
+            "--seed" => {
+                let value = value()?;
+                parsed.seed = Some(value.parse().map_err(|_| invalid(&value))?);
+            }
+            "--density" => {
+                let value = value()?;
+                parsed.density = Some(value.parse().map_err(|_| invalid(&value))?);
+            }
+            "--size" => {
+                let value = value()?;
+                parsed.size = Some(
+                    value
+                        .split_once('x')
+                        .and_then(|(w, h)| Some((w.parse().ok()?, h.parse().ok()?)))
+                        .filter(|(w, h)| *w > 0 && *h > 0)
+                        .ok_or_else(|| invalid(&value))?,
+                );
+            }
+            "--mode" => {
+                let value = value()?;
+                parsed.mode = ConsoleMode::from_name(&value).ok_or_else(|| invalid(&value))?;
+            }
+            "--color" => parsed.color = true,
+            "-h" | "--help" => return Err(String::new()),
+            _ => return Err(format!("Unknown option: {}", arg)),
+        }
+    }
+
+    Ok(parsed)
+}
+
+/// Terminal session
+struct Tui {
+    state: GameState,
+    console: ConsoleRenderer,
+    cursor: (usize, usize),
+    /// Top left corner of the view
+    view: (usize, usize),
+    /// Terminal size, as `(columns, rows)`
+    terminal_size: (usize, usize),
+}
+
+impl Tui {
+    fn new(state: GameState, args: &TuiArgs, terminal_size: (usize, usize)) -> Self {
+        let cursor = (state.width / 2, state.height / 2);
+        let mut console = ConsoleRenderer::new();
+        console.mode = args.mode;
+        console.color = args.color;
+
+        let mut tui = Self {
+            state,
+            console,
+            cursor,
+            view: (0, 0),
+            terminal_size,
+        };
+        tui.resize(terminal_size);
+        tui
+    }
+
+    /// Get the view size, in cells
+    fn view_size(&self) -> (usize, usize) {
+        let (columns, rows) = self.terminal_size;
+        let (cell_width, cell_height) = self.console.mode.cell_size();
+        (
+            (columns.saturating_sub(BORDER_COLUMNS) * cell_width).clamp(1, self.state.width),
+            (rows.saturating_sub(BORDER_ROWS) * cell_height).clamp(1, self.state.height),
+        )
+    }
+
+    fn resize(&mut self, terminal_size: (usize, usize)) {
+        self.terminal_size = terminal_size;
+        self.console.invalidate();
+        self.scroll_to_cursor();
+    }
+
+    /// Scroll the view so the cursor is visible
+    fn scroll_to_cursor(&mut self) {
+        let (width, height) = self.view_size();
+        let scroll = |view: usize, cursor: usize, size: usize, max: usize| {
+            let view = if cursor < view {
+                cursor
+            } else if cursor >= view + size {
+                cursor + 1 - size
+            } else {
+                view
+            };
+            view.min(max - size)
+        };
+
+        self.view = (
+            scroll(self.view.0, self.cursor.0, width, self.state.width),
+            scroll(self.view.1, self.cursor.1, height, self.state.height),
+        );
+        self.console.viewport = Some((self.view.0, self.view.1, width, height));
+        self.console.cursor = Some(self.cursor);
+    }
+
+    /// Move the cursor, wrapping around the grid edges
+    fn move_cursor(&mut self, dx: isize, dy: isize) {
+        let wrap = |pos: usize, d: isize, size: usize| {
+            (pos as isize + d).rem_euclid(size as isize) as usize
+        };
+        self.cursor = (
+            wrap(self.cursor.0, dx, self.state.width),
+            wrap(self.cursor.1, dy, self.state.height),
+        );
+        self.scroll_to_cursor();
+    }
+
+    fn change_speed(&mut self, factor: f32) {
+        let speed = self.state.scheduler.speed * factor;
+        self.state.scheduler.speed = speed.clamp(MIN_SPEED, MAX_SPEED);
+    }
+
+    /// Handle a key, returning `false` to quit
+    fn handle_key(&mut self, key: Key) -> bool {
+        let page = self.view_size().1 as isize;
+
+        match key {
+            Key::Char('q') | Key::Escape | Key::CtrlC => return false,
+            Key::Char(' ') => {
+                self.state.running = !self.state.running;
+                self.state.scheduler.cancel();
+            }
+            Key::Char('s') => {
+                self.state.running = false;
+                self.state.scheduler.step(1);
+            }
+            Key::Backspace if self.state.can_tick_back() => {
+                self.state.running = false;
+                self.state.tick_back();
+            }
+            Key::Char('+') | Key::Char('=') => self.change_speed(2.0),
+            Key::Char('-') => self.change_speed(0.5),
+            Key::Char('r') => {
+                self.state.randomize();
+                self.state.stats = GameStats::from_state(&self.state);
+            }
+            Key::Char('c') => {
+                self.state.clear();
+                self.state.stats = GameStats::from_state(&self.state);
+            }
+            Key::Char('t') | Key::Enter => {
+                let idx = self.state.pos_to_index(self.cursor);
+                let alive = self.state.data[idx];
+                self.state.set_value_at_pos(self.cursor, !alive);
+                self.state.stats = GameStats::from_state(&self.state);
+            }
+            Key::Char('m') => {
+                self.console.mode = self.console.mode.next();
+                self.scroll_to_cursor();
+            }
+            Key::Char('a') => self.console.color = !self.console.color,
+            Key::Up | Key::Char('k') => self.move_cursor(0, -1),
+            Key::Down | Key::Char('j') => self.move_cursor(0, 1),
+            Key::Left | Key::Char('h') => self.move_cursor(-1, 0),
+            Key::Right | Key::Char('l') => self.move_cursor(1, 0),
+            Key::PageUp => self.move_cursor(0, -page),
+            Key::PageDown => self.move_cursor(0, page),
+            Key::Home => self.move_cursor(-(self.cursor.0 as isize), 0),
+            Key::End => {
+                let end = self.state.width - 1 - self.cursor.0;
+                self.move_cursor(end as isize, 0);
+            }
+            _ => {}
+        }
+
+        true
+    }
+
+    fn render(&mut self) {
+        let state = &self.state;
+        let status = format!(
+            "{} | pop {} | {} | {} gen/s | ({}, {}) | {} | {} B/frame | space: pause, s: step, r: random, c: clear, t: toggle, m: mode, q: quit",
+            state.rule.notation(),
+            state.stats.population,
+            if state.running { "running" } else { "paused" },
+            state.scheduler.speed,
+            self.cursor.0,
+            self.cursor.1,
+            self.console.mode,
+            self.console.frame_bytes,
+        );
+        // Keep the status on one line
+        self.console.status = status.chars().take(self.terminal_size.0).collect();
+        self.console.render(&self.state);
+    }
+}
+
+fn setup_state(args: &TuiArgs, terminal_size: (usize, usize)) -> Result<GameState, String> {
+    let (cell_width, cell_height) = args.mode.cell_size();
+    let size = args.size.unwrap_or((
+        (terminal_size.0.saturating_sub(BORDER_COLUMNS) * cell_width).max(1),
+        (terminal_size.1.saturating_sub(BORDER_ROWS) * cell_height).max(1),
+    ));
+    let mut state = GameState::new(size);
+
+    if let Some(name) = &args.rule {
+        state.set_rule(Rule::from_name(name).ok_or_else(|| format!("Unknown rule: {}", name))?);
+    }
+    if let Some(density) = args.density {
+        state.soup.density = density;
+    }
+    match args.seed {
+        Some(seed) => state.randomize_with_seed(seed),
+        None => state.randomize(),
+    }
+
+    state.stats = GameStats::from_state(&state);
+    Ok(state)
+}
+
+/// Run the interactive terminal mode from command line arguments, returning
+/// the exit code
+pub fn run_tui(args: &[String]) -> i32 {
+    let args = match parse_args(args) {
+        Ok(args) => args,
+        Err(e) if e.is_empty() => {
+            println!("{}", USAGE);
+            return 0;
+        }
+        Err(e) => {
+            eprintln!("{}\n\n{}", e, USAGE);
+            return 1;
+        }
+    };
+
+    let terminal = match RawTerminal::enable() {
+        Ok(terminal) => terminal,
+        Err(e) => {
+            eprintln!("Could not use the terminal: {}", e);
+            return 1;
+        }
+    };
+    let terminal_size = terminal.size().unwrap_or((80, 24));
+
+    let state = match setup_state(&args, terminal_size) {
+        Ok(state) => state,
+        Err(e) => {
+            drop(terminal);
+            eprintln!("{}", e);
+            return 1;
+        }
+    };
+    let mut tui = Tui::new(state, &args, terminal_size);
+
+    let mut last_frame = Instant::now();
+    loop {
+        if let Some(size) = terminal.size() {
+            if size != tui.terminal_size {
+                tui.resize(size);
+            }
+        }
+
+        tui.render();
+
+        let keys = match terminal.read_keys(FRAME_DELAY) {
+            Ok(keys) => keys,
+            Err(e) => {
+                drop(terminal);
+                eprintln!("Could not read the terminal: {}", e);
+                return 1;
+            }
+        };
+        if !keys.into_iter().all(|key| tui.handle_key(key)) {
+            break;
+        }
+
+        let now = Instant::now();
+        let ticks = tui
+            .state
+            .scheduled_ticks(now.duration_since(last_frame).as_secs_f32());
+        last_frame = now;
+        tui.state.cycle(ticks);
+    }
+
+    0
+}
@@ -0,0 +1,197 @@
+//! Raw terminal access
+//!
+//! The terminal is switched to raw mode and to the alternate screen while a
+//! `RawTerminal` is alive, and restored when it is dropped, even on panic.
+
+use std::io::{self, Write};
+use std::mem;
+
+/// Terminal key
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Key {
+    Char(char),
+    Up,
+    Down,
+    Left,
+    Right,
+    PageUp,
+    PageDown,
+    Home,
+    End,
+    Enter,
+    Backspace,
+    Escape,
+    CtrlC,
+}
+
+/// Terminal in raw mode
+pub struct RawTerminal {
+    original: libc::termios,
+}
+
+impl RawTerminal {
+    /// Switch the terminal to raw mode
+    pub fn enable() -> io::Result<Self> {
+        unsafe {
+            if libc::isatty(libc::STDIN_FILENO) == 0 {
+                return Err(io::Error::other("standard input is not a terminal"));
+            }
+
+            let mut original: libc::termios = mem::zeroed();
+            if libc::tcgetattr(libc::STDIN_FILENO, &mut original) != 0 {
+                return Err(io::Error::last_os_error());
+            }
+
+            let mut raw = original;
+            libc::cfmakeraw(&mut raw);
+            if libc::tcsetattr(libc::STDIN_FILENO, libc::TCSANOW, &raw) != 0 {
+                return Err(io::Error::last_os_error());
+            }
+
+            // Alternate screen, hidden cursor
+            let mut stdout = io::stdout();
+            stdout.write_all(b"\x1B[?1049h\x1B[?25l")?;
+            stdout.flush()?;
+
+            Ok(Self { original })
+        }
+    }
+
+    /// Get the terminal size, as `(columns, rows)`
+    pub fn size(&self) -> Option<(usize, usize)> {
+        unsafe {
+            let mut size: libc::winsize = mem::zeroed();
+            if libc::ioctl(libc::STDOUT_FILENO, libc::TIOCGWINSZ, &mut size) != 0
+                || size.ws_col == 0
+                || size.ws_row == 0
+            {
+                return None;
+            }
+            Some((usize::from(size.ws_col), usize::from(size.ws_row)))
+        }
+    }
+
+    /// Wait up to `timeout_ms` for input, and get the keys read
+    pub fn read_keys(&self, timeout_ms: i32) -> io::Result<Vec<Key>> {
+        let mut fd = libc::pollfd {
+            fd: libc::STDIN_FILENO,
+            events: libc::POLLIN,
+            revents: 0,
+        };
+
+        let ready = unsafe { libc::poll(&mut fd, 1, timeout_ms) };
+        if ready < 0 {
+            let error = io::Error::last_os_error();
+            // Interrupted by a signal, like a terminal resize
+            return match error.kind() {
+                io::ErrorKind::Interrupted => Ok(vec![]),
+                _ => Err(error),
+            };
+        }
+        if ready == 0 {
+            return Ok(vec![]);
+        }
+
+        let mut buffer = [0u8; 64];
+        let count = unsafe {
+            libc::read(
+                libc::STDIN_FILENO,
+                buffer.as_mut_ptr() as *mut libc::c_void,
+                buffer.len(),
+            )
+        };
+        if count < 0 {
+            return Err(io::Error::last_os_error());
+        }
+
+        Ok(parse_keys(&buffer[..count as usize]))
+    }
+}
+
+impl Drop for RawTerminal {
+    fn drop(&mut self) {
+        let mut stdout = io::stdout();
+        let _ = stdout.write_all(b"\x1B[0m\x1B[?25h\x1B[?1049l");
+        let _ = stdout.flush();
+        unsafe {
+            libc::tcsetattr(libc::STDIN_FILENO, libc::TCSANOW, &self.original);
+        }
+    }
+}
+
+/// Parse raw input bytes into keys
+fn parse_keys(bytes: &[u8]) -> Vec<Key> {
+    let mut keys = vec![];
+    let text = String::from_utf8_lossy(bytes);
+    let mut chars = text.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        let key = match c {
+            '\x1B' if chars.peek() == Some(&'[') || chars.peek() == Some(&'O') => {
+                chars.next();
+                let mut sequence = String::new();
+                while let Some(&c) = chars.peek() {
+                    chars.next();
+                    sequence.push(c);
+                    if c.is_ascii_alphabetic() || c == '~' {
+                        break;
+                    }
+                }
+                match sequence.as_str() {
+                    "A" => Key::Up,
+                    "B" => Key::Down,
+                    "C" => Key::Right,
+                    "D" => Key::Left,
+                    "H" | "1~" => Key::Home,
+                    "F" | "4~" => Key::End,
+                    "5~" => Key::PageUp,
+                    "6~" => Key::PageDown,
+                    _ => continue,
+                }
+            }
+            '\x1B' => Key::Escape,
+            '\x03' => Key::CtrlC,
+            '\r' | '\n' => Key::Enter,
+            '\x7F' | '\x08' => Key::Backspace,
+            c if c.is_control() => continue,
+            c => Key::Char(c),
+        };
+        keys.push(key);
+    }
+
+    keys
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_keys() {
+        assert_eq!(
+            parse_keys(b"a\r\x7F\x03"),
+            vec![Key::Char('a'), Key::Enter, Key::Backspace, Key::CtrlC]
+        );
+    }
+
+    #[test]
+    fn test_parse_escape_sequences() {
+        assert_eq!(
+            parse_keys(b"\x1B[A\x1B[D\x1BOH\x1B[4~\x1B[5~\x1B[6~"),
+            vec![
+                Key::Up,
+                Key::Left,
+                Key::Home,
+                Key::End,
+                Key::PageUp,
+                Key::PageDown
+            ]
+        );
+
+        // Unknown sequences are skipped, a lone escape is a key
+        assert_eq!(
+            parse_keys(b"\x1B[2~x\x1B"),
+            vec![Key::Char('x'), Key::Escape]
+        );
+    }
+}
@@ -1,7 +1,25 @@
 mod gol;
 
-use self::gol::run;
+use std::env;
+use std::process;
+
+#[cfg(unix)]
+use self::gol::run_tui;
+use self::gol::{run, run_headless, run_search};
 
 fn main() {
-    run()
+    let args = env::args().skip(1).collect::<Vec<_>>();
+
+    match args.first().map(String::as_str) {
+        Some("run") => process::exit(run_headless(&args[1..])),
+        Some("search") => process::exit(run_search(&args[1..])),
+        #[cfg(unix)]
+        Some("tui") => process::exit(run_tui(&args[1..])),
+        #[cfg(not(unix))]
+        Some("tui") => {
+            eprintln!("The terminal mode is not supported on this platform");
+            process::exit(1)
+        }
+        _ => run(),
+    }
 }